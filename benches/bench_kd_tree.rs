@@ -0,0 +1,106 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use kd_tree::KdTree;
+use life_game::spatial::{GridIndex, KdTreeIndex, Space, SpatialIndex, GRID_BUCKET_SIZE};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+/// Random positions on a `size` x `size` grid.
+fn positions(rng: &mut StdRng, n: usize, size: i32) -> Vec<[i32; 2]> {
+    (0..n).map(|_| [rng.random_range(0..size), rng.random_range(0..size)]).collect()
+}
+
+/// Nearest prey lookups of one sweep over the 1002x1002 bench grid, with the prey tree rebuilt
+/// for each of the 9 phases or built once and shared by all of them.
+pub fn bench_kd_tree(c: &mut Criterion) {
+    let size = 1002;
+    let mut rng = StdRng::seed_from_u64(0);
+    let prey = positions(&mut rng, (size * size / 4) as usize, size);
+    let predators = positions(&mut rng, (size * size / 4) as usize, size);
+    let phases = predators.chunks(predators.len().div_ceil(9)).collect::<Vec<_>>();
+
+    c.bench_function("kd tree rebuilt per phase", |b| {
+        b.iter(|| {
+            for phase in &phases {
+                let tree = KdTree::build(prey.clone());
+                for predator in phase.iter() {
+                    criterion::black_box(tree.nearest(predator));
+                }
+            }
+        })
+    });
+    c.bench_function("kd tree built once per sweep", |b| {
+        b.iter(|| {
+            let tree = KdTree::build(prey.clone());
+            for phase in &phases {
+                for predator in phase.iter() {
+                    criterion::black_box(tree.nearest(predator));
+                }
+            }
+        })
+    });
+}
+
+/// Distinct random positions on the `height` first rows of a grid `width` wide, in row-major
+/// order like the simulation lists them.
+fn distinct_positions(rng: &mut StdRng, n: usize, width: i32, height: i32) -> Vec<(i32, i32)> {
+    let mut cells = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).collect::<Vec<_>>();
+    cells.shuffle(rng);
+    cells.truncate(n);
+    cells.sort_by_key(|&(x, y)| (y, x));
+    cells
+}
+
+/// One step of nearest prey lookups over the bounded 501x501 bench grid while 1% of the prey
+/// move: the full rebuild done before, against the indexes that only take in the moves. Run with
+/// prey on a quarter of the cells and, where buckets should shine, on three quarters of them.
+pub fn bench_prey_index(c: &mut Criterion) {
+    let size = 501;
+    let space = Space { width: size, height: size, toroidal: false };
+    let mut rng = StdRng::seed_from_u64(0);
+    let predators = positions(&mut rng, (size * size / 4) as usize, size);
+    for percent in [25, 75] {
+        let nb_prey = size * size * percent / 100;
+        let nb_moving = (nb_prey + 99) / 100;
+        // The bottom rows are kept free for the prey that move.
+        let first_free_row = size - (nb_moving + size - 1) / size;
+        let prey = distinct_positions(&mut rng, nb_prey as usize, size, first_free_row);
+        let mut moved = prey.iter().copied().skip(nb_moving as usize).collect::<Vec<_>>();
+        moved.extend((0..nb_moving).map(|i| (i % size, first_free_row + i / size)));
+
+        c.bench_function(&format!("prey index at {percent}%: kd tree rebuilt every step"), |b| {
+            let mut toggle = false;
+            b.iter(|| {
+                toggle = !toggle;
+                let current = if toggle { &moved } else { &prey };
+                let tree = KdTree::build(current.iter().map(|&(x, y)| [x, y]).collect());
+                for predator in &predators {
+                    criterion::black_box(tree.nearest(predator));
+                }
+            })
+        });
+        let mut indexes: Vec<(&str, Box<dyn SpatialIndex>)> = vec![
+            ("incremental kd tree", Box::new(KdTreeIndex::new(space, &prey))),
+            ("incremental grid", Box::new(GridIndex::new(space, GRID_BUCKET_SIZE, &prey))),
+        ];
+        for (name, index) in indexes.iter_mut() {
+            let mut toggle = false;
+            c.bench_function(&format!("prey index at {percent}%: {name}"), |b| {
+                b.iter(|| {
+                    toggle = !toggle;
+                    if toggle {
+                        index.update(&prey, &moved);
+                    } else {
+                        index.update(&moved, &prey);
+                    }
+                    for &[x, y] in &predators {
+                        criterion::black_box(index.nearest((x, y)));
+                    }
+                })
+            });
+        }
+    }
+}
+
+criterion_group!(benches, bench_kd_tree, bench_prey_index);
+criterion_main!(benches);
@@ -1,20 +1,23 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use life_game::simulation::Simulation;
+use life_game::config::SimulationConfig;
+use life_game::simulation::{get_cell, get_cell_ref, Simulation};
 
 fn create_test_simulation(w: i32, h: i32) -> Simulation {
-    let mut sim = Simulation::new(
-        w,
-        h,
-        0.1,
-        0.1,
-        0.1,
-        0.1,
-        0.1,
-        0.1,
-        20,
-        (w*h / 4) as u32,
-        (w*h / 4) as u32,
-    );
+    let mut sim = Simulation::new(SimulationConfig {
+        width: w,
+        height: h,
+        prey_reproduction_rate: 0.1,
+        prey_moving_rate: 0.1,
+        predator_reproduction_rate: 0.1,
+        predator_moving_rate: 0.1,
+        predator_hunting_rate: 0.1,
+        predator_death_rate: 0.1,
+        predator_death_after: 20,
+        nb_prey_init: (w*h / 4) as u32,
+        nb_predator_init: (w*h / 4) as u32,
+        ..Default::default()
+    })
+    .expect("valid config");
     sim.init_grid();
     sim
 }
@@ -50,5 +53,29 @@ pub fn bench_sim(c: &mut Criterion) {
     );
 }
 
-criterion_group!(benches, bench_sim);
+/// Looks up every cell of the 1002x1002 bench grid, mutably and read-only.
+pub fn bench_cell_lookup(c: &mut Criterion) {
+    let mut sim = create_test_simulation(1002, 1002);
+    sim.init_simulation();
+    c.bench_function("get_cell 1002x1002", |b| {
+        b.iter(|| {
+            for y in 0..1002 {
+                for x in 0..1002 {
+                    criterion::black_box(get_cell(&mut sim, x, y));
+                }
+            }
+        })
+    });
+    c.bench_function("get_cell_ref 1002x1002", |b| {
+        b.iter(|| {
+            for y in 0..1002 {
+                for x in 0..1002 {
+                    criterion::black_box(get_cell_ref(&sim, x, y));
+                }
+            }
+        })
+    });
+}
+
+criterion_group!(benches, bench_sim, bench_cell_lookup);
 criterion_main!(benches);
\ No newline at end of file
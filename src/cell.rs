@@ -1,46 +1,256 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-use crate::individual::Individual;
+use std::convert::Infallible;
+use std::ops::{Index, IndexMut};
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyAnyMethods, PyString};
+use pyo3::{Bound, FromPyObject, IntoPyObject, PyAny, PyResult, Python};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::individual::{Context, Individual, SavedIndividual};
+use crate::stats::{IndividualState, TraitKind};
 
+/// What a cell holds, see `Simulation::cell_kind`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellKind {
+    Empty,
+    Prey,
+    Predator,
+    Omnivore,
+    Obstacle,
+}
+
+impl CellKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CellKind::Empty => "empty",
+            CellKind::Prey => "prey",
+            CellKind::Predator => "predator",
+            CellKind::Omnivore => "omnivore",
+            CellKind::Obstacle => "obstacle",
+        }
+    }
+}
+
+impl<'py> IntoPyObject<'py> for CellKind {
+    type Target = PyString;
+    type Output = Bound<'py, PyString>;
+    type Error = Infallible;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        Ok(PyString::new(py, self.as_str()))
+    }
+}
+
+impl<'py> FromPyObject<'py> for CellKind {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        match ob.extract::<&str>()? {
+            "empty" => Ok(CellKind::Empty),
+            "prey" => Ok(CellKind::Prey),
+            "predator" => Ok(CellKind::Predator),
+            "omnivore" => Ok(CellKind::Omnivore),
+            "obstacle" => Ok(CellKind::Obstacle),
+            other => Err(PyValueError::new_err(format!("unknown cell kind {other:?}"))),
+        }
+    }
+}
+
+/// Mutable view over a run of consecutive cells of the grid, indexed with global cell indices.
+///
+/// Phases update rows whose neighbourhoods don't overlap, so each of them can work on its own
+/// view concurrently.
+pub(crate) struct GridSlice<'a> {
+    cells: &'a mut [Cell],
+    offset: usize,
+    /// The whole grid as it was at the start of the step, in `UpdateMode::Synchronous`.
+    previous: Option<&'a [Cell]>,
+}
+
+impl<'a> GridSlice<'a> {
+    /// View over `cells`, the first of which has index `offset`, with the individuals looking
+    /// around in `previous` if set, see `seen`.
+    pub(crate) fn new(cells: &'a mut [Cell], offset: usize, previous: Option<&'a [Cell]>) -> Self {
+        GridSlice { cells, offset, previous }
+    }
+
+    /// Cell `idx` as the individuals see it when they look around: as it was at the start of
+    /// the step in `UpdateMode::Synchronous`, as it is now otherwise. Changes always go to
+    /// `self[idx]`.
+    pub(crate) fn seen(&self, idx: usize) -> &Cell {
+        match self.previous {
+            Some(previous) => &previous[idx],
+            None => &self[idx],
+        }
+    }
+}
+
+impl Index<usize> for GridSlice<'_> {
+    type Output = Cell;
+
+    fn index(&self, idx: usize) -> &Cell {
+        &self.cells[idx - self.offset]
+    }
+}
+
+impl IndexMut<usize> for GridSlice<'_> {
+    fn index_mut(&mut self, idx: usize) -> &mut Cell {
+        &mut self.cells[idx - self.offset]
+    }
+}
+
+/// Saves the individual of a cell along with its species.
+fn serialize_content<S: Serializer>(content: &Option<Box<dyn Individual + Send + Sync>>, serializer: S) -> Result<S::Ok, S::Error> {
+    content.as_ref().map(|individual| individual.save()).serialize(serializer)
+}
+
+fn deserialize_content<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Box<dyn Individual + Send + Sync>>, D::Error> {
+    Ok(Option::<SavedIndividual>::deserialize(deserializer)?.map(SavedIndividual::into_individual))
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Cell {
     pub(crate) x: i32,
     pub(crate) y: i32,
-    pub content: Option<Box<dyn Individual + Send + Sync>>,
-    neighbours: Vec<Rc<RefCell<Cell>>>,
+    /// Individuals are plain data owned by their cell, there are no locks, see the threading
+    /// model in the crate docs.
+    #[serde(serialize_with = "serialize_content", deserialize_with = "deserialize_content")]
+    pub(crate) content: Option<Box<dyn Individual + Send + Sync>>,
+    neighbours: Vec<usize>,
+    /// Coordinates of `neighbours`, in the same order.
+    neighbour_positions: Vec<(i32, i32)>,
     pub(crate) is_empty: bool,
     pub(crate) is_predator: bool,
     pub(crate) is_prey: bool,
+    pub(crate) is_omnivore: bool,
+    pub(crate) grass: f32,
+    /// Left by prey, followed by predators with `TargetingMode::Scent`.
+    pub(crate) scent: f32,
+    /// Walls never hold individuals nor grass.
+    pub(crate) is_obstacle: bool,
+    /// The individual moved in or was born here during the current step, so it already had
+    /// its turn. Reset at the start of every step, so it isn't saved.
+    #[serde(skip)]
+    pub(crate) arrived: bool,
+}
+
+/// Deep copy: the clone holds its own copy of the individual.
+impl Clone for Cell {
+    fn clone(&self) -> Self {
+        Cell {
+            x: self.x,
+            y: self.y,
+            content: self.content.as_ref().map(|individual| individual.save().into_individual()),
+            neighbours: self.neighbours.clone(),
+            neighbour_positions: self.neighbour_positions.clone(),
+            is_empty: self.is_empty,
+            is_predator: self.is_predator,
+            is_prey: self.is_prey,
+            is_omnivore: self.is_omnivore,
+            grass: self.grass,
+            scent: self.scent,
+            is_obstacle: self.is_obstacle,
+            arrived: self.arrived,
+        }
+    }
 }
 
 impl Cell {
-    pub(crate) fn new(x: i32, y: i32) -> Self {
+    pub(crate) fn new(x: i32, y: i32, grass: f32) -> Self {
         Cell {
             x,
             y,
             content: None,
             neighbours: Vec::with_capacity(8),
+            neighbour_positions: Vec::with_capacity(8),
             is_empty: true,
             is_predator: false,
             is_prey: false,
+            is_omnivore: false,
+            grass,
+            scent: 0.0,
+            is_obstacle: false,
+            arrived: false,
         }
     }
     
-    pub(crate) fn update(&mut self, nearest_prey: Option<(i32, i32)>) {
-        if let Some(content) = &mut self.content {
-            let mut local_empty_cells = self.neighbours
+    /// Updates the individual living in `grid[idx]`, if any.
+    ///
+    /// The individual is taken out of its cell for the duration of its update so that it can
+    /// freely mutate the neighbouring cells; the cell flags are left untouched meanwhile.
+    ///
+    /// Free neighbouring cells are looked up when the individual's turn comes, so a cell claimed
+    /// by an earlier move or birth of the sweep is never handed out twice: the first claimant
+    /// wins and later ones only see the cells still free.
+    pub(crate) fn update(grid: &mut GridSlice, idx: usize, nearest: Option<(i32, i32)>, ctx: &mut Context) {
+        if let Some(mut content) = grid[idx].content.take() {
+            let neighbours = grid[idx].neighbours.clone();
+            // Free to take: nobody claimed the cell earlier in the step, and in
+            // `UpdateMode::Synchronous` it was already free when the step started.
+            let mut local_empty_cells = neighbours
                 .iter()
-                .filter(|cell| cell.borrow_mut().is_empty())
-                .map(Rc::clone)
+                .copied()
+                .filter(|&n| grid[n].is_empty() && grid.seen(n).is_empty())
                 .collect::<Vec<_>>();
-            
-            let is_dead = content.update(nearest_prey, &mut self.neighbours, &mut local_empty_cells);
+            // Random picks index this list, so its order must not depend on how the
+            // neighbours were listed.
+            local_empty_cells.sort_by_key(|&n| (grid[n].y, grid[n].x));
+            let is_dead = content.update(idx, nearest, &neighbours, &mut local_empty_cells, grid, ctx);
             if is_dead {
-                self.empty();
+                grid[idx].empty();
+            } else {
+                grid[idx].content = Some(content);
             }
         }
     }
-    pub(crate) fn add_neighbour(&mut self, neighbour: Rc<RefCell<Cell>>) {
+    pub(crate) fn regrow_grass(&mut self, rate: f32, max: f32) {
+        if !self.is_obstacle {
+            self.grass = (self.grass + rate).min(max);
+        }
+    }
+
+    /// Turns the cell into a wall, removing its occupant if any.
+    pub(crate) fn set_obstacle(&mut self) {
+        self.empty();
+        self.is_obstacle = true;
+        self.grass = 0.0;
+        self.scent = 0.0;
+    }
+
+    pub(crate) fn add_neighbour(&mut self, neighbour: usize, pos: (i32, i32)) {
         self.neighbours.push(neighbour);
+        self.neighbour_positions.push(pos);
+    }
+
+    pub(crate) fn clear_neighbours(&mut self) {
+        self.neighbours.clear();
+        self.neighbour_positions.clear();
+    }
+
+    pub(crate) fn neighbours(&self) -> &[usize] {
+        &self.neighbours
+    }
+
+    /// Coordinates of the linked neighbours, in link order.
+    pub fn neighbour_positions(&self) -> Vec<(i32, i32)> {
+        self.neighbour_positions.clone()
+    }
+
+    pub(crate) fn place_prey(&mut self, prey: Box<dyn Individual + Send + Sync>) {
+        self.content = Some(prey);
+        self.is_empty = false;
+        self.is_prey = true;
+        self.arrived = true;
+    }
+
+    pub(crate) fn place_predator(&mut self, predator: Box<dyn Individual + Send + Sync>) {
+        self.content = Some(predator);
+        self.is_empty = false;
+        self.is_predator = true;
+        self.arrived = true;
+    }
+
+    pub(crate) fn place_omnivore(&mut self, omnivore: Box<dyn Individual + Send + Sync>) {
+        self.content = Some(omnivore);
+        self.is_empty = false;
+        self.is_omnivore = true;
+        self.arrived = true;
     }
 
     pub(crate) fn empty(&mut self) {
@@ -48,10 +258,11 @@ impl Cell {
         self.is_empty = true;
         self.is_predator = false;
         self.is_prey = false;
+        self.is_omnivore = false;
     }
 
-    fn is_empty(&self) -> bool {
-        self.is_empty
+    pub(crate) fn is_empty(&self) -> bool {
+        self.is_empty && !self.is_obstacle
     }
 
     pub(crate) fn is_prey(&self) -> bool {
@@ -61,4 +272,54 @@ impl Cell {
     pub(crate) fn is_predator(&self) -> bool {
         self.is_predator
     }
-}
\ No newline at end of file
+
+    pub(crate) fn is_omnivore(&self) -> bool {
+        self.is_omnivore
+    }
+
+    pub(crate) fn age(&self) -> Option<u32> {
+        self.content.as_ref().map(|content| content.age())
+    }
+
+    pub(crate) fn hunger(&self) -> Option<u32> {
+        self.content.as_ref().and_then(|content| content.hunger())
+    }
+
+    pub(crate) fn is_infected(&self) -> bool {
+        self.content.as_ref().is_some_and(|content| content.is_infected())
+    }
+
+    pub(crate) fn trait_value(&self, which: TraitKind) -> Option<f32> {
+        self.content.as_ref().and_then(|content| content.trait_value(which))
+    }
+
+    pub(crate) fn state(&self) -> Option<IndividualState> {
+        self.content.as_ref().map(|content| content.state())
+    }
+
+    pub(crate) fn kind(&self) -> CellKind {
+        if self.is_prey {
+            CellKind::Prey
+        } else if self.is_predator {
+            CellKind::Predator
+        } else if self.is_omnivore {
+            CellKind::Omnivore
+        } else if self.is_obstacle {
+            CellKind::Obstacle
+        } else {
+            CellKind::Empty
+        }
+    }
+
+    /// Stable integer encoding of the cell content: 0 = empty, 1 = prey, 2 = predator,
+    /// 3 = obstacle, 4 = omnivore.
+    pub(crate) fn kind_code(&self) -> u8 {
+        match self.kind() {
+            CellKind::Empty => 0,
+            CellKind::Prey => 1,
+            CellKind::Predator => 2,
+            CellKind::Obstacle => 3,
+            CellKind::Omnivore => 4,
+        }
+    }
+}
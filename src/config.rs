@@ -0,0 +1,587 @@
+use std::fmt;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pymethods, PyErr};
+use serde::{Deserialize, Serialize};
+
+/// Shape of the neighbourhood of a cell.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Neighbourhood {
+    /// Cells within Chebyshev distance `neighbourhood_radius`, diagonals included.
+    Moore,
+    /// Cells within Manhattan distance `neighbourhood_radius`, i.e. 4-connected at radius 1.
+    VonNeumann,
+}
+
+/// Shape of the cells of the grid.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topology {
+    /// Square cells, linked following `neighbourhood`.
+    Square,
+    /// Hexagonal cells in the "odd-r" layout, odd rows shifted half a cell right, each touching
+    /// 6 others; `neighbourhood_radius` counts hex steps and `neighbourhood` and
+    /// `allow_diagonal_moves` are ignored. On a torus the height must be even for the rows to
+    /// line up across the top and bottom edges.
+    Hex,
+}
+
+/// What lies beyond the edges of the grid.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoundaryMode {
+    /// Opposite edges are joined, the grid is a torus.
+    Toroidal,
+    /// The grid is a closed box: edge cells have fewer neighbours.
+    Bounded,
+}
+
+/// How many parents a newborn needs.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReproductionMode {
+    /// A lone individual breeds a copy of itself.
+    Asexual,
+    /// A neighbour of the same species is needed, the newborn blends the factors of both.
+    Sexual,
+}
+
+/// How predators find the prey to chase.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetingMode {
+    /// Straight to the nearest prey within `predator_vision_radius`.
+    KdTree,
+    /// Up the gradient of the scent left by prey, see `scent_deposit`.
+    Scent,
+}
+
+/// Index answering the nearest prey queries, see `spatial::SpatialIndex`. Both pick the same
+/// prey, so the choice only changes speed: buckets are quicker on crowded grids, the tree keeps
+/// up when prey are few and far apart.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PreyIndex {
+    KdTree,
+    /// Uniform buckets of `spatial::GRID_BUCKET_SIZE` cells a side.
+    Grid,
+}
+
+/// Where the factors of a newborn come from, before any mutation.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InheritanceMode {
+    /// The factors of the parent, averaged with the partner's in sexual reproduction.
+    FromParent,
+    /// The rates of the config, whatever the parents drifted to.
+    FromConfig,
+}
+
+/// Which empty neighbour a newborn is placed on.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlacementStrategy {
+    Random,
+    /// Prey offspring take the grassiest empty neighbour and predator offspring the one closest
+    /// to the prey their parent chases. Ties, and parents chasing nothing, pick at random.
+    /// Omnivores always place theirs at random.
+    Greedy,
+}
+
+/// What kills predators, besides old age.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PredatorMortality {
+    /// Death with probability `predator_death_rate` at each step, however fed.
+    Random,
+    /// Starvation once hunger reaches `predator_death_after`.
+    Hunger,
+    Both,
+}
+
+/// Where `init_simulation` places the initial individuals. Individuals of a species take the
+/// free cells in random order among those the layout prefers for them.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InitialLayout {
+    /// Anywhere on the grid.
+    Random,
+    /// Packed around the centre of the grid, prey innermost.
+    CentralCluster,
+    /// Prey in every other band of `STRIPE_WIDTH` columns, predators and omnivores in the bands
+    /// in between.
+    Stripes,
+    /// Two clusters, around the middle of the left and right halves of the grid.
+    Colonies,
+}
+
+/// Width of the bands of `InitialLayout::Stripes`.
+pub const STRIPE_WIDTH: i32 = 4;
+
+/// Order in which a predator tries to catch the prey around it.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HuntOrder {
+    /// In the order the neighbours of its cell were linked.
+    Linked,
+    /// Closest first, ties in link order. Only differs from `Linked` when
+    /// `neighbourhood_radius` is above 1.
+    Nearest,
+    Random,
+}
+
+/// When an individual sees the moves made before it during the same step.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpdateMode {
+    /// Double-buffered: individuals look around in a copy of the grid taken at the start of the
+    /// step and their moves, births and kills go to the grid itself, so none of them shows
+    /// before the next step and cells freed during the step can't be entered. Claims on the
+    /// same cell go to the first individual updated, and a prey that moved away before its
+    /// predator's turn escapes it.
+    Synchronous,
+    /// Individuals act on the grid as left by those updated before them.
+    Asynchronous,
+}
+
+/// Parameters of a simulation run.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    #[pyo3(get, set)]
+    pub width: i32,
+    #[pyo3(get, set)]
+    pub height: i32,
+    #[pyo3(get, set)]
+    pub prey_reproduction_rate: f32,
+    #[pyo3(get, set)]
+    pub prey_moving_rate: f32,
+    #[pyo3(get, set)]
+    pub predator_reproduction_rate: f32,
+    #[pyo3(get, set)]
+    pub predator_moving_rate: f32,
+    #[pyo3(get, set)]
+    pub predator_hunting_rate: f32,
+    #[pyo3(get, set)]
+    pub predator_death_rate: f32,
+    /// Hunger at which a predator starves, reached after `predator_death_after / hunger_increment`
+    /// steps without eating.
+    #[pyo3(get, set)]
+    pub predator_death_after: u32,
+    /// Hunger a predator gains at each step without a kill.
+    #[pyo3(get, set)]
+    pub hunger_increment: u32,
+    /// Hunger each kill takes off a predator, down to 0; `None` clears it whatever it was.
+    #[pyo3(get, set)]
+    pub nutrition_per_prey: Option<u32>,
+    #[pyo3(get, set)]
+    pub predator_mortality: PredatorMortality,
+    /// Hunger of the initial predators and of newborns; `None` starts them half way to
+    /// `predator_death_after`.
+    #[pyo3(get, set)]
+    pub predator_initial_hunger: Option<u32>,
+    /// Predators only breed, and move, while their hunger is below this; `None` sets it to half
+    /// of `predator_death_after`.
+    #[pyo3(get, set)]
+    pub predator_reproduce_below_hunger: Option<u32>,
+    /// Each neighbouring predator, up to three, raises the hunting factor of a predator by this
+    /// fraction of itself.
+    #[pyo3(get, set)]
+    pub pack_bonus: f32,
+    /// Prey a predator can eat in a single step; 0 stops predators from hunting.
+    #[pyo3(get, set)]
+    pub max_kills_per_step: u32,
+    #[pyo3(get, set)]
+    pub hunt_order: HuntOrder,
+    #[pyo3(get, set)]
+    pub nb_prey_init: u32,
+    #[pyo3(get, set)]
+    pub initial_layout: InitialLayout,
+    #[pyo3(get, set)]
+    pub nb_predator_init: u32,
+    #[pyo3(get, set)]
+    pub nb_omnivore_init: u32,
+    #[pyo3(get, set)]
+    pub omnivore_reproduction_rate: f32,
+    #[pyo3(get, set)]
+    pub omnivore_moving_rate: f32,
+    #[pyo3(get, set)]
+    pub omnivore_hunting_rate: f32,
+    /// Number of steps without eating after which an omnivore starves.
+    #[pyo3(get, set)]
+    pub omnivore_death_after: u32,
+    /// Hunger an omnivore loses by eating grass instead of prey.
+    #[pyo3(get, set)]
+    pub omnivore_grass_gain: u32,
+    /// Age at which an omnivore dies of old age.
+    #[pyo3(get, set)]
+    pub omnivore_max_age: u32,
+    /// Steps between the conception and the birth of a prey; 0 gives birth at once.
+    #[pyo3(get, set)]
+    pub prey_gestation: u32,
+    /// Steps between the conception and the birth of a predator; 0 gives birth at once.
+    #[pyo3(get, set)]
+    pub predator_gestation: u32,
+    /// A prey breeds only with between `prey_reproduction_min_neighbours` and
+    /// `prey_reproduction_max_neighbours` prey around it, bounds included.
+    #[pyo3(get, set)]
+    pub prey_reproduction_min_neighbours: u32,
+    #[pyo3(get, set)]
+    pub prey_reproduction_max_neighbours: u32,
+    /// Each step a prey dies with probability `crowding_death_rate` for each neighbouring prey
+    /// beyond `crowding_threshold`, capped at 1; grass plays no part.
+    #[pyo3(get, set)]
+    pub crowding_death_rate: f32,
+    #[pyo3(get, set)]
+    pub crowding_threshold: u32,
+    /// Each step a healthy prey catches the disease with probability `infection_rate` from each
+    /// infected neighbouring prey. Prey that recover are immune for life.
+    #[pyo3(get, set)]
+    pub infection_rate: f32,
+    /// Steps a prey stays infected before recovering.
+    #[pyo3(get, set)]
+    pub infection_duration: u32,
+    /// Probability for an infected prey to die of the disease at each step.
+    #[pyo3(get, set)]
+    pub infection_death_rate: f32,
+    /// Fraction of the initial prey that start infected.
+    #[pyo3(get, set)]
+    pub initial_infected_fraction: f32,
+    /// Same as the prey bounds, counting neighbouring predators.
+    #[pyo3(get, set)]
+    pub predator_reproduction_min_neighbours: u32,
+    #[pyo3(get, set)]
+    pub predator_reproduction_max_neighbours: u32,
+    /// Same as the prey bounds, counting neighbouring omnivores.
+    #[pyo3(get, set)]
+    pub omnivore_reproduction_min_neighbours: u32,
+    #[pyo3(get, set)]
+    pub omnivore_reproduction_max_neighbours: u32,
+    /// Energy of a newborn or freshly fed prey; a prey dies when it runs out.
+    #[pyo3(get, set)]
+    pub prey_max_energy: u32,
+    #[pyo3(get, set)]
+    pub prey_energy_cost_reproduce: u32,
+    /// Energy a prey loses, or hunger a predator or omnivore gains, each time it moves.
+    #[pyo3(get, set)]
+    pub move_energy_cost: u32,
+    /// Largest number of prey: births stop when it is reached. 0 sets no limit.
+    #[pyo3(get, set)]
+    pub max_prey: u32,
+    /// Largest number of predators, 0 sets no limit.
+    #[pyo3(get, set)]
+    pub max_predators: u32,
+    /// Largest number of omnivores, 0 sets no limit.
+    #[pyo3(get, set)]
+    pub max_omnivores: u32,
+    /// Probability for a prey that moves to head for the centre of the neighbouring prey rather
+    /// than a random cell.
+    #[pyo3(get, set)]
+    pub prey_cohesion: f32,
+    /// Prey move away from predators closer than this distance; 0 disables fleeing.
+    #[pyo3(get, set)]
+    pub prey_flee_radius: i32,
+    /// Predators and omnivores ignore prey farther than this Chebyshev distance and wander
+    /// instead. Seeing a prey only steers the chase: they still hunt within their neighbourhood.
+    #[pyo3(get, set)]
+    pub predator_vision_radius: i32,
+    /// Chance per step that a predator with no prey in its neighbourhood jumps to an empty cell
+    /// near the centroid of all prey.
+    #[pyo3(get, set)]
+    pub migration_rate: f32,
+    #[pyo3(get, set)]
+    pub targeting: TargetingMode,
+    #[pyo3(get, set)]
+    pub prey_index: PreyIndex,
+    #[pyo3(get, set)]
+    pub update_mode: UpdateMode,
+    /// Scent a prey leaves on its cell at each step.
+    #[pyo3(get, set)]
+    pub scent_deposit: f32,
+    /// Fraction of the scent of a cell that fades at each step.
+    #[pyo3(get, set)]
+    pub scent_decay: f32,
+    /// Fraction of the scent of a cell that mixes with its neighbours at each step.
+    #[pyo3(get, set)]
+    pub scent_diffusion: f32,
+    /// Age at which a prey dies of old age.
+    #[pyo3(get, set)]
+    pub prey_max_age: u32,
+    /// Age at which a predator dies of old age.
+    #[pyo3(get, set)]
+    pub predator_max_age: u32,
+    /// Grass added to every cell at each step, up to `grass_max`.
+    #[pyo3(get, set)]
+    pub grass_regrowth_rate: f32,
+    #[pyo3(get, set)]
+    pub grass_max: f32,
+    /// Grass a prey eats from its cell at each step; it only eats, and refills its energy, when
+    /// that much is left.
+    #[pyo3(get, set)]
+    pub graze_amount: f32,
+    /// A prey only gives birth while at least this much grass is left on its cell, after
+    /// grazing, and each birth eats that much of it.
+    #[pyo3(get, set)]
+    pub reproduce_grass_threshold: f32,
+    /// Individuals only sense, move and breed within the neighbourhood of their cell.
+    #[pyo3(get, set)]
+    pub neighbourhood: Neighbourhood,
+    #[pyo3(get, set)]
+    pub topology: Topology,
+    #[pyo3(get, set)]
+    pub neighbourhood_radius: i32,
+    /// When off, individuals only move straight along an axis. Diagonal neighbours still count
+    /// for breeding, crowding and hunting.
+    #[pyo3(get, set)]
+    pub allow_diagonal_moves: bool,
+    #[pyo3(get, set)]
+    pub boundary: BoundaryMode,
+    /// Fraction of the cells turned into walls by `init_simulation`.
+    #[pyo3(get, set)]
+    pub obstacle_fraction: f32,
+    #[pyo3(get, set)]
+    pub reproduction_mode: ReproductionMode,
+    #[pyo3(get, set)]
+    pub birth_placement: PlacementStrategy,
+    #[pyo3(get, set)]
+    pub inheritance: InheritanceMode,
+    /// Probability for each factor of a newborn to mutate away from its parent's value.
+    #[pyo3(get, set)]
+    pub mutation_rate: f32,
+    /// Standard deviation of the gaussian noise added to a mutated factor.
+    #[pyo3(get, set)]
+    pub mutation_std: f32,
+    /// Steps in a cycle of seasons; 0 disables seasons.
+    #[pyo3(get, set)]
+    pub season_length: u32,
+    /// Reproduction and grass regrowth are scaled by `1 + season_amplitude * sin(...)` over the
+    /// cycle: up to `1 + season_amplitude` in summer, down to `1 - season_amplitude` in winter.
+    #[pyo3(get, set)]
+    pub season_amplitude: f32,
+    /// Update independent rows of a phase on several threads, with the same outcome as a serial
+    /// run. Ignored while a `max_*` population cap is set.
+    #[pyo3(get, set)]
+    pub parallel: bool,
+    /// Record the population counts after every step, see `Simulation::population_history`.
+    #[pyo3(get, set)]
+    pub record_history: bool,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            width: 100,
+            height: 100,
+            prey_reproduction_rate: 0.5,
+            prey_moving_rate: 0.5,
+            predator_reproduction_rate: 0.5,
+            predator_moving_rate: 0.5,
+            predator_hunting_rate: 0.5,
+            predator_death_rate: 0.1,
+            predator_death_after: 25,
+            hunger_increment: 1,
+            nutrition_per_prey: None,
+            predator_mortality: PredatorMortality::Both,
+            predator_initial_hunger: None,
+            predator_reproduce_below_hunger: None,
+            pack_bonus: 0.0,
+            max_kills_per_step: 1,
+            hunt_order: HuntOrder::Linked,
+            nb_prey_init: 1500,
+            initial_layout: InitialLayout::Random,
+            nb_predator_init: 1000,
+            nb_omnivore_init: 0,
+            omnivore_reproduction_rate: 0.3,
+            omnivore_moving_rate: 0.5,
+            omnivore_hunting_rate: 0.25,
+            omnivore_death_after: 25,
+            omnivore_grass_gain: 2,
+            omnivore_max_age: 100,
+            prey_gestation: 0,
+            predator_gestation: 0,
+            prey_reproduction_min_neighbours: 0,
+            prey_reproduction_max_neighbours: 3,
+            crowding_death_rate: 0.0,
+            crowding_threshold: 4,
+            infection_rate: 0.0,
+            infection_duration: 10,
+            infection_death_rate: 0.0,
+            initial_infected_fraction: 0.0,
+            predator_reproduction_min_neighbours: 0,
+            predator_reproduction_max_neighbours: 3,
+            omnivore_reproduction_min_neighbours: 0,
+            omnivore_reproduction_max_neighbours: 3,
+            prey_max_energy: 10,
+            prey_energy_cost_reproduce: 2,
+            move_energy_cost: 0,
+            max_prey: 0,
+            max_predators: 0,
+            max_omnivores: 0,
+            prey_cohesion: 0.0,
+            prey_flee_radius: 0,
+            predator_vision_radius: i32::MAX,
+            migration_rate: 0.0,
+            targeting: TargetingMode::KdTree,
+            prey_index: PreyIndex::KdTree,
+            update_mode: UpdateMode::Asynchronous,
+            scent_deposit: 1.0,
+            scent_decay: 0.1,
+            scent_diffusion: 0.5,
+            prey_max_age: 100,
+            predator_max_age: 100,
+            grass_regrowth_rate: 1.0,
+            grass_max: 10.0,
+            graze_amount: 1.0,
+            reproduce_grass_threshold: 0.0,
+            neighbourhood: Neighbourhood::Moore,
+            topology: Topology::Square,
+            neighbourhood_radius: 1,
+            allow_diagonal_moves: true,
+            boundary: BoundaryMode::Toroidal,
+            obstacle_fraction: 0.0,
+            reproduction_mode: ReproductionMode::Sexual,
+            birth_placement: PlacementStrategy::Random,
+            inheritance: InheritanceMode::FromParent,
+            mutation_rate: 0.0,
+            mutation_std: 0.05,
+            season_length: 0,
+            season_amplitude: 0.5,
+            parallel: true,
+            record_history: true,
+        }
+    }
+}
+
+/// Why a `SimulationConfig` can't be simulated.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    /// A probability outside of `[0, 1]`.
+    RateOutOfRange { field: &'static str, value: f32 },
+    /// A width or height of zero or less.
+    NonPositiveDimension { field: &'static str, value: i32 },
+    /// An odd height on a hex torus, whose rows can't line up across the top and bottom edges.
+    OddHexTorusHeight { height: i32 },
+    /// A radius below the smallest one that makes sense for its field.
+    RadiusTooSmall { field: &'static str, value: i32, min: i32 },
+    /// A lower neighbour-count bound above its upper bound, so that nobody could ever breed.
+    InvertedBounds { min_field: &'static str, min: u32, max_field: &'static str, max: u32 },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::RateOutOfRange { field, value } => {
+                write!(f, "{field} must be in [0, 1], got {value}")
+            }
+            ConfigError::NonPositiveDimension { field, value } => {
+                write!(f, "{field} must be positive, got {value}")
+            }
+            ConfigError::OddHexTorusHeight { height } => {
+                write!(f, "height must be even on a toroidal hex grid, got {height}")
+            }
+            ConfigError::RadiusTooSmall { field, value, min } => {
+                write!(f, "{field} must be at least {min}, got {value}")
+            }
+            ConfigError::InvertedBounds { min_field, min, max_field, max } => {
+                write!(f, "{min_field} ({min}) must not be above {max_field} ({max})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for PyErr {
+    fn from(err: ConfigError) -> PyErr {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+impl SimulationConfig {
+    /// Hunger a predator starts with, see `predator_initial_hunger`.
+    pub(crate) fn predator_start_hunger(&self) -> u32 {
+        self.predator_initial_hunger.unwrap_or(self.predator_death_after / 2)
+    }
+
+    /// Hunger from which predators stop breeding, see `predator_reproduce_below_hunger`.
+    pub(crate) fn predator_breeding_hunger(&self) -> u32 {
+        self.predator_reproduce_below_hunger.unwrap_or(self.predator_death_after / 2)
+    }
+
+    /// Checks that the dimensions are positive, and even on a hex torus, that the radii and the
+    /// neighbour-count bounds make sense and that every rate is a probability.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (field, value) in [("width", self.width), ("height", self.height)] {
+            if value <= 0 {
+                return Err(ConfigError::NonPositiveDimension { field, value });
+            }
+        }
+        if self.topology == Topology::Hex && self.boundary == BoundaryMode::Toroidal && self.height % 2 == 1 {
+            return Err(ConfigError::OddHexTorusHeight { height: self.height });
+        }
+        let radii = [
+            ("neighbourhood_radius", self.neighbourhood_radius, 1),
+            ("prey_flee_radius", self.prey_flee_radius, 0),
+            ("predator_vision_radius", self.predator_vision_radius, 0),
+        ];
+        for (field, value, min) in radii {
+            if value < min {
+                return Err(ConfigError::RadiusTooSmall { field, value, min });
+            }
+        }
+        let bounds = [
+            ("prey_reproduction_min_neighbours", self.prey_reproduction_min_neighbours, "prey_reproduction_max_neighbours", self.prey_reproduction_max_neighbours),
+            ("predator_reproduction_min_neighbours", self.predator_reproduction_min_neighbours, "predator_reproduction_max_neighbours", self.predator_reproduction_max_neighbours),
+            ("omnivore_reproduction_min_neighbours", self.omnivore_reproduction_min_neighbours, "omnivore_reproduction_max_neighbours", self.omnivore_reproduction_max_neighbours),
+        ];
+        for (min_field, min, max_field, max) in bounds {
+            if min > max {
+                return Err(ConfigError::InvertedBounds { min_field, min, max_field, max });
+            }
+        }
+        let rates = [
+            ("prey_reproduction_rate", self.prey_reproduction_rate),
+            ("prey_moving_rate", self.prey_moving_rate),
+            ("predator_reproduction_rate", self.predator_reproduction_rate),
+            ("predator_moving_rate", self.predator_moving_rate),
+            ("predator_hunting_rate", self.predator_hunting_rate),
+            ("predator_death_rate", self.predator_death_rate),
+            ("omnivore_reproduction_rate", self.omnivore_reproduction_rate),
+            ("omnivore_moving_rate", self.omnivore_moving_rate),
+            ("omnivore_hunting_rate", self.omnivore_hunting_rate),
+            ("obstacle_fraction", self.obstacle_fraction),
+            ("mutation_rate", self.mutation_rate),
+            ("season_amplitude", self.season_amplitude),
+            ("prey_cohesion", self.prey_cohesion),
+            ("crowding_death_rate", self.crowding_death_rate),
+            ("infection_rate", self.infection_rate),
+            ("infection_death_rate", self.infection_death_rate),
+            ("initial_infected_fraction", self.initial_infected_fraction),
+            ("migration_rate", self.migration_rate),
+            ("scent_decay", self.scent_decay),
+            ("scent_diffusion", self.scent_diffusion),
+        ];
+        for (field, value) in rates {
+            check_rate(field, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns `value` if it is a probability, `RateOutOfRange` otherwise.
+pub(crate) fn check_rate(field: &'static str, value: f32) -> Result<f32, ConfigError> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(ConfigError::RateOutOfRange { field, value })
+    }
+}
+
+#[pymethods]
+impl SimulationConfig {
+    #[new]
+    fn py_new() -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,55 @@
+use pyo3::types::{PyDict, PyDictMethods};
+use pyo3::{Bound, IntoPyObject, PyErr, Python};
+use serde::{Deserialize, Serialize};
+
+use crate::cell::CellKind;
+
+/// What happened to an individual, see `Event`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventKind {
+    Birth,
+    /// Any death: eaten, starved, out of energy or of old age.
+    Death,
+    /// A predator or an omnivore ate a prey; the prey gets its own `Death`.
+    Kill,
+    /// Logged at the cell the individual moved to.
+    Move,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Birth => "birth",
+            EventKind::Death => "death",
+            EventKind::Kill => "kill",
+            EventKind::Move => "move",
+        }
+    }
+}
+
+/// One entry of the event log, see `Simulation::enable_event_log`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    /// Step during which it happened, counting from 1.
+    pub step: usize,
+    pub kind: EventKind,
+    pub x: i32,
+    pub y: i32,
+    pub species: CellKind,
+}
+
+impl<'py> IntoPyObject<'py> for Event {
+    type Target = PyDict;
+    type Output = Bound<'py, PyDict>;
+    type Error = PyErr;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        let dict = PyDict::new(py);
+        dict.set_item("step", self.step)?;
+        dict.set_item("kind", self.kind.as_str())?;
+        dict.set_item("x", self.x)?;
+        dict.set_item("y", self.y)?;
+        dict.set_item("species", self.species)?;
+        Ok(dict)
+    }
+}
@@ -0,0 +1,71 @@
+//! Distances on the grid when opposite edges are joined, see `BoundaryMode::Toroidal`.
+
+use crate::simulation::Pos;
+
+/// `x` brought back into `0..len`.
+pub fn wrap(x: i32, len: i32) -> i32 {
+    x.rem_euclid(len)
+}
+
+/// Signed length of the shortest way from `from` to `to` on a wrapping axis of length `len`.
+pub fn toroidal_delta(from: i32, to: i32, len: i32) -> i32 {
+    let delta = wrap(to - from, len);
+    if delta > len / 2 { delta - len } else { delta }
+}
+
+/// Squared euclidean distance between `a` and `b` the short way round a `width` by `height`
+/// torus.
+pub fn toroidal_distance_sq(a: Pos, b: Pos, width: i32, height: i32) -> i32 {
+    let dx = toroidal_delta(a.0, b.0, width);
+    let dy = toroidal_delta(a.1, b.1, height);
+    dx * dx + dy * dy
+}
+
+/// Offsets of the 6 cells touching a cell of row `y` on a hex grid, see `Topology::Hex`.
+pub fn hex_neighbour_offsets(y: i32) -> [Pos; 6] {
+    if y & 1 == 0 {
+        [(-1, 0), (1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)]
+    } else {
+        [(-1, 0), (1, 0), (0, -1), (1, -1), (0, 1), (1, 1)]
+    }
+}
+
+/// Number of steps between two cells of a hex grid, see `Topology::Hex`, ignoring the wrap.
+pub fn hex_distance(a: Pos, b: Pos) -> i32 {
+    // Axial coordinates, in which a step changes q, r or both by 1 in opposite directions.
+    let axial = |(x, y): Pos| (x - (y - (y & 1)) / 2, y);
+    let (aq, ar) = axial(a);
+    let (bq, br) = axial(b);
+    let (dq, dr) = (aq - bq, ar - br);
+    (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances_go_the_short_way_across_the_edges() {
+        assert_eq!(toroidal_delta(9, 0, 10), 1);
+        assert_eq!(toroidal_delta(0, 9, 10), -1);
+        assert_eq!(toroidal_delta(2, 7, 10), 5);
+        assert_eq!(toroidal_delta(3, 5, 10), 2);
+        assert_eq!(toroidal_distance_sq((9, 0), (0, 9), 10, 10), 2);
+        assert_eq!(toroidal_distance_sq((0, 5), (9, 5), 10, 20), 1);
+        assert_eq!(toroidal_distance_sq((1, 1), (3, 4), 10, 10), 13);
+        assert_eq!(wrap(-1, 10), 9);
+        assert_eq!(wrap(10, 10), 0);
+    }
+
+    #[test]
+    fn hex_neighbours_are_one_step_away() {
+        for y in [-3, 0, 1, 4] {
+            for (dx, dy) in hex_neighbour_offsets(y) {
+                assert_eq!(hex_distance((2, y), (2 + dx, y + dy)), 1, "row {y}");
+            }
+        }
+        assert_eq!(hex_distance((0, 0), (0, 2)), 2);
+        assert_eq!(hex_distance((0, 0), (3, 0)), 3);
+        assert_eq!(hex_distance((0, 1), (2, 3)), 3);
+    }
+}
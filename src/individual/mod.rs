@@ -1,10 +1,245 @@
 pub(crate) mod prey;
 pub(crate) mod predator;
+pub(crate) mod omnivore;
 
-use std::cell::RefCell;
-use std::rc::Rc;
-use crate::cell::Cell;
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+use crate::cell::{Cell, CellKind, GridSlice};
+use crate::events::{Event, EventKind};
+use crate::geom::{hex_distance, hex_neighbour_offsets, toroidal_delta, wrap};
+use crate::config::{BoundaryMode, InheritanceMode, SimulationConfig, Topology};
+use crate::individual::omnivore::Omnivore;
+use crate::individual::predator::Predator;
+use crate::individual::prey::Prey;
+use crate::stats::{IndividualState, StepStats, TraitKind};
+
+/// Number of individuals of each species, updated with the births of the step.
+///
+/// Rows of a phase run concurrently and all count their births here, so a population cap
+/// holds across the whole grid.
+#[derive(Default)]
+pub(crate) struct Census {
+    pub(crate) prey: AtomicUsize,
+    pub(crate) predators: AtomicUsize,
+    pub(crate) omnivores: AtomicUsize,
+}
+
+impl Census {
+    /// Whether `prey`, `predators` and `omnivores` more births would all fit under the `max_*`
+    /// caps of `config`.
+    pub(crate) fn has_room_for(&self, config: &SimulationConfig, prey: usize, predators: usize, omnivores: usize) -> bool {
+        let fits = |count: &AtomicUsize, max: u32, births: usize| max == 0 || count.load(Ordering::Relaxed) + births <= max as usize;
+        fits(&self.prey, config.max_prey, prey)
+            && fits(&self.predators, config.max_predators, predators)
+            && fits(&self.omnivores, config.max_omnivores, omnivores)
+    }
+}
+
+/// Simulation-wide state shared with every individual during a step.
+pub(crate) struct Context<'a> {
+    pub(crate) config: &'a SimulationConfig,
+    pub(crate) rng: &'a mut StdRng,
+    pub(crate) stats: StepStats,
+    /// Scale of the reproduction factors during the current season.
+    pub(crate) season_factor: f32,
+    pub(crate) census: &'a Census,
+    /// Events of the step, `None` when the event log is off.
+    pub(crate) events: Option<Vec<Event>>,
+}
+
+impl Context<'_> {
+    /// Logs an event at `cell`; the simulation sets its step.
+    pub(crate) fn log(&mut self, kind: EventKind, cell: &Cell, species: CellKind) {
+        if let Some(events) = &mut self.events {
+            events.push(Event { step: 0, kind, x: cell.x, y: cell.y, species });
+        }
+    }
+
+    /// Counts a newborn of `kind`, or suppresses the birth when its species already reached its
+    /// `max_*` population.
+    pub(crate) fn admit_birth(&mut self, kind: CellKind) -> bool {
+        let (count, max) = match kind {
+            CellKind::Prey => (&self.census.prey, self.config.max_prey),
+            CellKind::Predator => (&self.census.predators, self.config.max_predators),
+            CellKind::Omnivore => (&self.census.omnivores, self.config.max_omnivores),
+            CellKind::Empty | CellKind::Obstacle => return true,
+        };
+        if max == 0 {
+            return true;
+        }
+        let admitted = count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| (n < max as usize).then_some(n + 1))
+            .is_ok();
+        if !admitted {
+            self.stats.births_suppressed += 1;
+        }
+        admitted
+    }
+}
+
+/// Squared euclidean length of the shortest way between two grid positions, following the
+/// boundary mode.
+pub(crate) fn distance_sq(a: (i32, i32), b: (i32, i32), config: &SimulationConfig) -> i32 {
+    let (dx, dy) = offset(a, b, config);
+    dx * dx + dy * dy
+}
+
+/// Shortest offset from `from` to `to`, following the boundary mode.
+pub(crate) fn offset(from: (i32, i32), to: (i32, i32), config: &SimulationConfig) -> (i32, i32) {
+    match config.boundary {
+        BoundaryMode::Toroidal => (
+            toroidal_delta(from.0, to.0, config.width),
+            toroidal_delta(from.1, to.1, config.height),
+        ),
+        BoundaryMode::Bounded => (to.0 - from.0, to.1 - from.1),
+    }
+}
+
+/// Cell one step away from `from` on the shortest way to `to`, following the boundary mode.
+/// Without diagonal moves the step is taken along the axis with the longer way to go.
+pub(crate) fn chase_step(from: (i32, i32), to: (i32, i32), config: &SimulationConfig) -> (i32, i32) {
+    let (mut dx, mut dy) = offset(from, to, config);
+    if config.topology == Topology::Hex {
+        if (dx, dy) == (0, 0) {
+            return from;
+        }
+        // Towards the image of `to` closest to `from`, so the way may cross the edges.
+        let target = (from.0 + dx, from.1 + dy);
+        let (sx, sy) = hex_neighbour_offsets(from.1)
+            .into_iter()
+            .min_by_key(|&(sx, sy)| hex_distance((from.0 + sx, from.1 + sy), target))
+            .expect("hex cells have neighbours");
+        return (wrap(from.0 + sx, config.width), wrap(from.1 + sy, config.height));
+    }
+    if !config.allow_diagonal_moves && dx != 0 && dy != 0 {
+        if dx.abs() >= dy.abs() {
+            dy = 0;
+        } else {
+            dx = 0;
+        }
+    }
+    (wrap(from.0 + dx.signum(), config.width), wrap(from.1 + dy.signum(), config.height))
+}
+
+/// Empty cells the individual in `grid[idx]` may move to: all of `local_empty_cells`, or only
+/// those straight along an axis without `allow_diagonal_moves`.
+pub(crate) fn move_targets<'a>(idx: usize, local_empty_cells: &'a [usize], grid: &GridSlice, config: &SimulationConfig) -> Cow<'a, [usize]> {
+    if config.allow_diagonal_moves || config.topology == Topology::Hex {
+        return Cow::Borrowed(local_empty_cells);
+    }
+    let pos = (grid[idx].x, grid[idx].y);
+    Cow::Owned(
+        local_empty_cells
+            .iter()
+            .copied()
+            .filter(|&n| {
+                let (dx, dy) = offset(pos, (grid[n].x, grid[n].y), config);
+                dx == 0 || dy == 0
+            })
+            .collect(),
+    )
+}
+
+/// Factor passed from the parents to their offspring, with a chance of gaussian mutation.
+///
+/// With a partner the offspring starts from the mean of both parents' factors, and from
+/// `baseline`, the config rate, with `InheritanceMode::FromConfig`.
+pub(crate) fn inherit(factor: f32, partner: Option<f32>, baseline: f32, ctx: &mut Context) -> f32 {
+    let config = ctx.config;
+    let factor = match config.inheritance {
+        InheritanceMode::FromParent => partner.map_or(factor, |other| (factor + other) / 2.0),
+        InheritanceMode::FromConfig => baseline,
+    };
+    if config.mutation_rate <= 0.0 || ctx.rng.random::<f32>() >= config.mutation_rate {
+        return factor;
+    }
+    let noise = Normal::new(0.0, config.mutation_std).map_or(0.0, |normal| normal.sample(ctx.rng));
+    (factor + noise).clamp(0.0, 1.0)
+}
+
+/// An individual with its concrete species, as it is saved along with the simulation.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) enum SavedIndividual {
+    Prey(Prey),
+    Predator(Predator),
+    Omnivore(Omnivore),
+}
+
+impl SavedIndividual {
+    pub(crate) fn into_individual(self) -> Box<dyn Individual + Send + Sync> {
+        match self {
+            SavedIndividual::Prey(prey) => Box::new(prey),
+            SavedIndividual::Predator(predator) => Box::new(predator),
+            SavedIndividual::Omnivore(omnivore) => Box::new(omnivore),
+        }
+    }
+}
 
 pub(crate) trait Individual{
-    fn update(&mut self, nearest_prey: Option<(i32, i32)>, local_contents: &mut [Rc<RefCell<Cell>>], local_empty_cells: &mut Vec<Rc<RefCell<Cell>>>) -> bool;
+    /// Updates the individual living in `grid[idx]` and returns whether that cell must be emptied,
+    /// either because the individual died or because it moved elsewhere.
+    ///
+    /// `nearest` is the nearest prey for a predator and the nearest threatening predator for a prey.
+    /// `local_contents` and `local_empty_cells` are indices into `grid`.
+    fn update(&mut self, idx: usize, nearest: Option<(i32, i32)>, local_contents: &[usize], local_empty_cells: &mut Vec<usize>, grid: &mut GridSlice, ctx: &mut Context) -> bool;
+
+    fn age(&self) -> u32;
+
+    /// Current hunger for individuals that have one.
+    fn hunger(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether the individual carries the disease, see `infection_rate`.
+    fn is_infected(&self) -> bool {
+        false
+    }
+
+    /// Value of a heritable factor, `None` if it belongs to the other species.
+    fn trait_value(&self, which: TraitKind) -> Option<f32>;
+
+    /// Snapshot of the internal state, for inspection.
+    fn state(&self) -> IndividualState;
+
+    /// Copy of the individual that can be serialized.
+    fn save(&self) -> SavedIndividual;
+}
+
+/// Runs `f` on a grid of the size of `config` with `grass` on every cell, with a seeded context.
+#[cfg(test)]
+pub(crate) fn with_grid<R>(config: &SimulationConfig, grass: f32, f: impl FnOnce(&mut GridSlice, &mut Context) -> R) -> R {
+    use rand::SeedableRng;
+
+    let mut cells = (0..config.height)
+        .flat_map(|y| (0..config.width).map(move |x| Cell::new(x, y, grass)))
+        .collect::<Vec<_>>();
+    let mut grid = GridSlice::new(&mut cells, 0, None);
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut ctx = Context {
+        config,
+        rng: &mut rng,
+        stats: Default::default(),
+        season_factor: 1.0,
+        census: &Census::default(),
+        events: None,
+    };
+    f(&mut grid, &mut ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn census_has_room_until_a_cap_would_be_passed() {
+        let config = SimulationConfig { max_prey: 10, ..Default::default() };
+        let census = Census { prey: 7.into(), predators: 50.into(), omnivores: 100.into() };
+        assert!(census.has_room_for(&config, 3, 1000, 1000));
+        assert!(!census.has_room_for(&config, 4, 0, 0));
+    }
 }
@@ -0,0 +1,170 @@
+use rand::prelude::IndexedRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::cell::{Cell, CellKind, GridSlice};
+use crate::config::{ReproductionMode, SimulationConfig};
+use crate::individual::{chase_step, inherit, move_targets, Context, Individual, SavedIndividual};
+use crate::events::EventKind;
+use crate::stats::{IndividualState, TraitKind};
+
+/// Grass an omnivore eats from its cell when it caught no prey.
+const GRASS_EATEN_PER_STEP: f32 = 1.0;
+
+/// Hunts prey like a predator, but falls back on the grass of its cell when it catches nothing.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Omnivore {
+    reproduction_factor: f32,
+    moving_factor: f32,
+    hunting_factor: f32,
+    hunger: u32,
+    age: u32,
+}
+
+impl Omnivore {
+    pub(crate) fn new(config: &SimulationConfig) -> Self {
+        Omnivore {
+            reproduction_factor: config.omnivore_reproduction_rate,
+            moving_factor: config.omnivore_moving_rate,
+            hunting_factor: config.omnivore_hunting_rate,
+            hunger: config.omnivore_death_after / 2,
+            age: 0,
+        }
+    }
+
+    fn offspring(&self, partner: Option<&Cell>, ctx: &mut Context) -> Self {
+        let partner_trait = |which| partner.and_then(|cell| cell.trait_value(which));
+        Omnivore {
+            reproduction_factor: inherit(self.reproduction_factor, partner_trait(TraitKind::OmnivoreReproduction), ctx.config.omnivore_reproduction_rate, ctx),
+            moving_factor: inherit(self.moving_factor, partner_trait(TraitKind::OmnivoreMoving), ctx.config.omnivore_moving_rate, ctx),
+            hunting_factor: inherit(self.hunting_factor, partner_trait(TraitKind::OmnivoreHunting), ctx.config.omnivore_hunting_rate, ctx),
+            hunger: ctx.config.omnivore_death_after / 2,
+            age: 0,
+        }
+    }
+
+    /// Eats a neighbouring prey, or else the grass of its own cell.
+    fn eat(&mut self, idx: usize, local_contents: &[usize], local_empty_cells: &mut Vec<usize>, grid: &mut GridSlice, ctx: &mut Context) {
+        for &n in local_contents {
+            let rng_num: f32 = ctx.rng.random();
+            // A prey seen here that moved away in the meantime escapes.
+            if grid.seen(n).is_prey() && grid[n].is_prey() && rng_num < self.hunting_factor {
+                self.hunger = 0;
+                grid[n].empty();
+                ctx.stats.prey_eaten += 1;
+                ctx.log(EventKind::Death, &grid[n], CellKind::Prey);
+                ctx.log(EventKind::Kill, &grid[idx], CellKind::Omnivore);
+                local_empty_cells.push(n);
+                return;
+            }
+        }
+        let grass = &mut grid[idx].grass;
+        if *grass >= GRASS_EATEN_PER_STEP {
+            *grass -= GRASS_EATEN_PER_STEP;
+            self.hunger = self.hunger.saturating_sub(ctx.config.omnivore_grass_gain);
+        }
+    }
+
+    fn reproduce(&mut self, local_contents: &[usize], local_empty_cells: &[usize], grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        let nbr_omnivores = local_contents.iter().filter(|&&n| grid.seen(n).is_omnivore()).count() as u32;
+        let config = ctx.config;
+        let crowding = config.omnivore_reproduction_min_neighbours..=config.omnivore_reproduction_max_neighbours;
+        let sexual = ctx.config.reproduction_mode == ReproductionMode::Sexual;
+        let partner = local_contents.iter().copied().find(|&n| sexual && grid.seen(n).is_omnivore());
+        if (sexual && partner.is_none()) || !crowding.contains(&nbr_omnivores) {
+            return false;
+        }
+        if ctx.rng.random::<f32>() < self.reproduction_factor * ctx.season_factor {
+            if let Some(&n) = local_empty_cells.choose(ctx.rng) {
+                if !ctx.admit_birth(CellKind::Omnivore) {
+                    return false;
+                }
+                let offspring = self.offspring(partner.map(|p| grid.seen(p)), ctx);
+                grid[n].place_omnivore(Box::new(offspring));
+                ctx.stats.omnivores_born += 1;
+                ctx.log(EventKind::Birth, &grid[n], CellKind::Omnivore);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn move_to(&self, idx: usize, nearest_prey_pos: Option<(i32, i32)>, local_empty_cells: &[usize], grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        let local_empty_cells = &*move_targets(idx, local_empty_cells, grid, ctx.config);
+        if ctx.rng.random::<f32>() > self.moving_factor {
+            return false;
+        }
+        let target = nearest_prey_pos.and_then(|prey| {
+            let (x, y) = chase_step((grid[idx].x, grid[idx].y), prey, ctx.config);
+            local_empty_cells.iter().copied().find(|&n| grid[n].x == x && grid[n].y == y)
+        });
+        match target.or_else(|| local_empty_cells.choose(ctx.rng).copied()) {
+            Some(n) => {
+                let moved = Omnivore { hunger: self.hunger + ctx.config.move_energy_cost, ..self.clone() };
+                grid[n].place_omnivore(Box::new(moved));
+                ctx.log(EventKind::Move, &grid[n], CellKind::Omnivore);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Individual for Omnivore {
+    fn update(&mut self, idx: usize, nearest_prey: Option<(i32, i32)>, local_contents: &[usize], local_empty_cells: &mut Vec<usize>, grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        self.age += 1;
+        if self.age >= ctx.config.omnivore_max_age {
+            ctx.stats.deaths_by_old_age += 1;
+            ctx.log(EventKind::Death, &grid[idx], CellKind::Omnivore);
+            return true;
+        }
+        self.hunger += 1;
+        self.eat(idx, local_contents, local_empty_cells, grid, ctx);
+        if self.hunger >= ctx.config.omnivore_death_after {
+            ctx.stats.omnivores_starved += 1;
+            ctx.log(EventKind::Death, &grid[idx], CellKind::Omnivore);
+            return true;
+        }
+        if local_empty_cells.is_empty() {
+            ctx.stats.blocked_moves += 1;
+            return false;
+        }
+        if self.reproduce(local_contents, local_empty_cells, grid, ctx) {
+            return false;
+        }
+        self.move_to(idx, nearest_prey, local_empty_cells, grid, ctx)
+    }
+
+    fn age(&self) -> u32 {
+        self.age
+    }
+
+    fn hunger(&self) -> Option<u32> {
+        Some(self.hunger)
+    }
+
+    fn trait_value(&self, which: TraitKind) -> Option<f32> {
+        match which {
+            TraitKind::OmnivoreMoving => Some(self.moving_factor),
+            TraitKind::OmnivoreReproduction => Some(self.reproduction_factor),
+            TraitKind::OmnivoreHunting => Some(self.hunting_factor),
+            _ => None,
+        }
+    }
+
+    fn state(&self) -> IndividualState {
+        IndividualState {
+            species: CellKind::Omnivore,
+            age: self.age,
+            hunger: Some(self.hunger),
+            energy: None,
+            reproduction_factor: self.reproduction_factor,
+            moving_factor: self.moving_factor,
+            hunting_factor: Some(self.hunting_factor),
+        }
+    }
+
+    fn save(&self) -> SavedIndividual {
+        SavedIndividual::Omnivore(self.clone())
+    }
+}
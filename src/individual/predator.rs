@@ -1,100 +1,179 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-use rand::prelude::IndexedMutRandom;
+use rand::prelude::IndexedRandom;
+use rand::seq::SliceRandom;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::cell::Cell;
-use crate::individual::Individual;
+use crate::cell::{Cell, CellKind, GridSlice};
+use crate::config::{HuntOrder, PlacementStrategy, PredatorMortality, ReproductionMode, SimulationConfig, TargetingMode};
+use crate::individual::{chase_step, distance_sq, inherit, move_targets, Context, Individual, SavedIndividual};
+use crate::events::EventKind;
+use crate::stats::{IndividualState, TraitKind};
 
-#[derive(Clone)]
+/// Largest number of neighbouring predators that help a hunt, see `pack_bonus`.
+const MAX_PACK_HELPERS: usize = 3;
+
+/// Empty neighbour with the strongest scent, if it smells stronger than the cell `idx`.
+fn follow_scent(idx: usize, local_empty_cells: &[usize], grid: &GridSlice, ctx: &Context) -> Option<usize> {
+    if ctx.config.targeting != TargetingMode::Scent {
+        return None;
+    }
+    local_empty_cells
+        .iter()
+        .copied()
+        .max_by(|&a, &b| grid.seen(a).scent.total_cmp(&grid.seen(b).scent))
+        .filter(|&n| grid.seen(n).scent > grid.seen(idx).scent)
+}
+
+/// Random one of the cells of `local_empty_cells` closest to `target`.
+fn closest_cell(target: (i32, i32), local_empty_cells: &[usize], grid: &GridSlice, ctx: &mut Context) -> Option<usize> {
+    let distance = |n: usize| distance_sq((grid[n].x, grid[n].y), target, ctx.config);
+    let closest = local_empty_cells.iter().map(|&n| distance(n)).min()?;
+    let candidates = local_empty_cells.iter().copied().filter(|&n| distance(n) == closest).collect::<Vec<_>>();
+    candidates.choose(ctx.rng).copied()
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Predator {
-    x: i32,
-    y: i32,
     reproduction_factor: f32,
     moving_factor: f32,
     hunting_factor: f32,
     hunger: u32,
-    death_rate: f32,
-    max_hunger: u32,
-    sim_width: i32,
-    sim_height: i32,
+    age: u32,
+    /// Age at which `unborn` is born.
+    pregnant_until: u32,
+    unborn: Option<Box<Predator>>,
 }
 
 impl Predator {
-    pub(crate) fn new(x: i32, y: i32, reproduction_factor: f32, moving_factor: f32, hunting_factor: f32, death_rate: f32, max_hunger: u32, sim_width: i32, sim_height: i32) -> Self {
+    pub(crate) fn new(config: &SimulationConfig) -> Self {
+        Predator {
+            reproduction_factor: config.predator_reproduction_rate,
+            moving_factor: config.predator_moving_rate,
+            hunting_factor: config.predator_hunting_rate,
+            hunger: config.predator_start_hunger(),
+            age: 0,
+            pregnant_until: 0,
+            unborn: None,
+        }
+    }
+
+    fn offspring(&self, partner: Option<&Cell>, ctx: &mut Context) -> Self {
+        let partner_trait = |which| partner.and_then(|cell| cell.trait_value(which));
         Predator {
-            x,
-            y,
-            reproduction_factor,
-            moving_factor,
-            hunting_factor,
-            hunger: max_hunger/2,
-            death_rate,
-            max_hunger,
-            sim_width,
-            sim_height,
+            reproduction_factor: inherit(self.reproduction_factor, partner_trait(TraitKind::PredatorReproduction), ctx.config.predator_reproduction_rate, ctx),
+            moving_factor: inherit(self.moving_factor, partner_trait(TraitKind::PredatorMoving), ctx.config.predator_moving_rate, ctx),
+            hunting_factor: inherit(self.hunting_factor, partner_trait(TraitKind::PredatorHunting), ctx.config.predator_hunting_rate, ctx),
+            hunger: ctx.config.predator_start_hunger(),
+            age: 0,
+            pregnant_until: 0,
+            unborn: None,
         }
     }
 
     
-    fn hunt(&mut self, local_contents: &mut [Rc<RefCell<Cell>>], local_empty_cell: &mut Vec<Rc<RefCell<Cell>>>) -> bool {
-        for cell in local_contents.iter_mut() {
-            let rng_num: f32 = rand::rng().random();
-            if cell.borrow().is_prey() &&  rng_num < self.hunting_factor {
-                self.hunger = 0;
-                cell.borrow_mut().empty();
-                local_empty_cell.push(Rc::clone(cell));
-                return true;
+    /// Eats the neighbouring prey it catches, up to `max_kills_per_step` of them. A predator that
+    /// ate at the previous step is still full and doesn't hunt.
+    fn hunt(&mut self, idx: usize, local_contents: &[usize], local_empty_cell: &mut Vec<usize>, grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        if self.hunger == 0 {
+            return false;
+        }
+        let helpers = local_contents.iter().filter(|&&n| grid.seen(n).is_predator()).count().min(MAX_PACK_HELPERS);
+        let hunting_factor = self.hunting_factor * (1.0 + ctx.config.pack_bonus * helpers as f32);
+        let mut order = local_contents.to_vec();
+        match ctx.config.hunt_order {
+            HuntOrder::Linked => {}
+            HuntOrder::Nearest => {
+                let pos = (grid[idx].x, grid[idx].y);
+                order.sort_by_key(|&n| distance_sq(pos, (grid[n].x, grid[n].y), ctx.config));
             }
+            HuntOrder::Random => order.shuffle(ctx.rng),
         }
-        false
+        let mut kills = 0;
+        for n in order {
+            if kills >= ctx.config.max_kills_per_step {
+                break;
+            }
+            let rng_num: f32 = ctx.rng.random();
+            // A prey seen here that moved away in the meantime escapes.
+            if grid.seen(n).is_prey() && grid[n].is_prey() && rng_num < hunting_factor {
+                self.hunger = ctx.config.nutrition_per_prey.map_or(0, |nutrition| self.hunger.saturating_sub(nutrition));
+                grid[n].empty();
+                ctx.stats.prey_eaten += 1;
+                ctx.log(EventKind::Death, &grid[n], CellKind::Prey);
+                ctx.log(EventKind::Kill, &grid[idx], CellKind::Predator);
+                local_empty_cell.push(n);
+                kills += 1;
+            }
+        }
+        kills > 0
     }
     
-    fn reproduce(&mut self, local_contents: &[Rc<RefCell<Cell>>], local_empty_cells: &mut [Rc<RefCell<Cell>>]) -> bool {
-        let nbr_predators = local_contents.iter().filter(|cell| cell.borrow().is_predator()).count();
-        let rng_num: f32 = rand::rng().random();
-        if nbr_predators == 0 || nbr_predators >= 4 {
+    /// Conceives an offspring if not already pregnant, then gives birth once the gestation is over.
+    fn reproduce(&mut self, nearest_prey: Option<(i32, i32)>, local_contents: &[usize], local_empty_cells: &[usize], grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        if self.unborn.is_none() && !self.conceive(local_contents, local_empty_cells, grid, ctx) {
+            return false;
+        }
+        if self.age < self.pregnant_until {
             return false;
         }
-        else if rng_num < self.reproduction_factor {
-            if let Some(cell) = local_empty_cells.choose_mut(&mut rand::rng()) {
-                let new_x = cell.borrow().x;
-                let new_y = cell.borrow().y;
-                cell.borrow_mut().content = Some(Box::new(Predator::new(new_x, new_y, self.reproduction_factor, self.moving_factor, self.hunting_factor, self.death_rate, self.max_hunger, self.sim_width, self.sim_height)));
-                cell.borrow_mut().is_empty = false;
-                cell.borrow_mut().is_predator = true;
-                return true;
+        let birth_cell = match (ctx.config.birth_placement, nearest_prey) {
+            (PlacementStrategy::Greedy, Some(prey)) => closest_cell(prey, local_empty_cells, grid, ctx),
+            _ => local_empty_cells.choose(ctx.rng).copied(),
+        };
+        if let Some(n) = birth_cell {
+            if !ctx.admit_birth(CellKind::Predator) {
+                return false;
             }
+            grid[n].place_predator(self.unborn.take().unwrap());
+            ctx.stats.predators_born += 1;
+            ctx.log(EventKind::Birth, &grid[n], CellKind::Predator);
+            return true;
         }
         false
     }
 
-    fn move_to(&self, nearest_prey_pos: Option<(i32, i32)>, local_empty_cells: &mut [Rc<RefCell<Cell>>]) -> bool {
+    fn conceive(&mut self, local_contents: &[usize], local_empty_cells: &[usize], grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        let nbr_predators = local_contents.iter().filter(|&&n| grid.seen(n).is_predator()).count() as u32;
+        let config = ctx.config;
+        let crowding = config.predator_reproduction_min_neighbours..=config.predator_reproduction_max_neighbours;
+        let rng_num: f32 = ctx.rng.random();
+        let sexual = ctx.config.reproduction_mode == ReproductionMode::Sexual;
+        let partner = local_contents.iter().copied().find(|&n| sexual && grid.seen(n).is_predator());
+        if (sexual && partner.is_none()) || !crowding.contains(&nbr_predators) {
+            return false;
+        }
+        else if rng_num < self.reproduction_factor * ctx.season_factor && !local_empty_cells.is_empty() {
+            let offspring = self.offspring(partner.map(|p| grid.seen(p)), ctx);
+            self.unborn = Some(Box::new(offspring));
+            self.pregnant_until = self.age + ctx.config.predator_gestation;
+            return true;
+        }
+        false
+    }
+
+    /// Places a copy of the predator, hungrier by `move_energy_cost`, on the cell it moves to.
+    fn move_into(&self, n: usize, grid: &mut GridSlice, ctx: &mut Context) {
+        grid[n].place_predator(Box::new(Predator { hunger: self.hunger + ctx.config.move_energy_cost, ..self.clone() }));
+        ctx.log(EventKind::Move, &grid[n], CellKind::Predator);
+    }
+
+    fn move_to(&self, idx: usize, nearest_prey_pos: Option<(i32, i32)>, local_empty_cells: &[usize], grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        let local_empty_cells = &*move_targets(idx, local_empty_cells, grid, ctx.config);
         if let Some((x, y)) = nearest_prey_pos {
-            let dx: i32 = match x.cmp(&self.x) {
-                std::cmp::Ordering::Greater => 1,
-                std::cmp::Ordering::Equal => 0,
-                std::cmp::Ordering::Less => -1,
-            };
-            let dy: i32 = match y.cmp(&self.y) {
-                std::cmp::Ordering::Greater => 1,
-                std::cmp::Ordering::Equal => 0,
-                std::cmp::Ordering::Less => -1,
-            };
-            let new_x = (self.x + dx) % self.sim_width;
-            let new_y = (self.y + dy) % self.sim_height;
-            for cell in local_empty_cells.iter_mut() {
-                if cell.borrow().x == new_x && cell.borrow().y == new_y {
-                    cell.borrow_mut().content = Some(Box::new(self.clone()));
-                    cell.borrow_mut().is_empty = false;
-                    cell.borrow_mut().is_predator = true;
+            let (new_x, new_y) = chase_step((grid[idx].x, grid[idx].y), (x, y), ctx.config);
+            for &n in local_empty_cells {
+                if grid[n].x == new_x && grid[n].y == new_y {
+                    self.move_into(n, grid, ctx);
                     return true;
                 }
             }
-        } else if let Some(cell) = local_empty_cells.choose_mut(&mut rand::rng()) {
-            cell.borrow_mut().content = Some(Box::new(self.clone()));
-            cell.borrow_mut().is_empty = false;
-            cell.borrow_mut().is_predator = true;
+        } else if let Some(n) = follow_scent(idx, local_empty_cells, grid, ctx) {
+            self.move_into(n, grid, ctx);
+            return true;
+        } else if ctx.rng.random::<f32>() > self.moving_factor {
+            return false;
+        } else if let Some(&n) = local_empty_cells.choose(ctx.rng) {
+            self.move_into(n, grid, ctx);
             return true;
         }
         false
@@ -102,19 +181,241 @@ impl Predator {
 }
 
 impl Individual for Predator {
-    fn update(&mut self, nearest_prey: Option<(i32, i32)>, local_contents: &mut [Rc<RefCell<Cell>>], local_empty_cells: &mut Vec<Rc<RefCell<Cell>>>) -> bool {
-        self.hunger += 1;
-        let rng_num: f32 = rand::rng().random();
-        if rng_num < self.death_rate || self.hunger >= self.max_hunger {
+    fn update(&mut self, idx: usize, nearest_prey: Option<(i32, i32)>, local_contents: &[usize], local_empty_cells: &mut Vec<usize>, grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        self.age += 1;
+        if self.age >= ctx.config.predator_max_age {
+            ctx.stats.deaths_by_old_age += 1;
+            ctx.log(EventKind::Death, &grid[idx], CellKind::Predator);
             return true;
         }
-        self.hunt(local_contents, local_empty_cells); 
-        if self.hunger >= self.max_hunger / 2 || local_empty_cells.is_empty(){
+        let mortality = ctx.config.predator_mortality;
+        if mortality != PredatorMortality::Hunger && ctx.rng.random::<f32>() < ctx.config.predator_death_rate {
+            ctx.log(EventKind::Death, &grid[idx], CellKind::Predator);
+            return true;
+        }
+        if !self.hunt(idx, local_contents, local_empty_cells, grid, ctx) {
+            self.hunger += ctx.config.hunger_increment;
+        }
+        let max_hunger = ctx.config.predator_death_after;
+        if mortality != PredatorMortality::Random && self.hunger >= max_hunger {
+            ctx.stats.predators_starved += 1;
+            ctx.log(EventKind::Death, &grid[idx], CellKind::Predator);
+            return true;
+        }
+        if self.hunger >= ctx.config.predator_breeding_hunger() {
+            return false;
+        }
+        if local_empty_cells.is_empty() {
+            ctx.stats.blocked_moves += 1;
             return false;
         }
-        if self.reproduce(local_contents, local_empty_cells) {
+        if self.reproduce(nearest_prey, local_contents, local_empty_cells, grid, ctx) {
             return false;
         }
-        self.move_to(nearest_prey, local_empty_cells)
+        self.move_to(idx, nearest_prey, local_empty_cells, grid, ctx)
     }
-}
\ No newline at end of file
+
+    fn age(&self) -> u32 {
+        self.age
+    }
+
+    fn hunger(&self) -> Option<u32> {
+        Some(self.hunger)
+    }
+
+    fn trait_value(&self, which: TraitKind) -> Option<f32> {
+        match which {
+            TraitKind::PredatorMoving => Some(self.moving_factor),
+            TraitKind::PredatorReproduction => Some(self.reproduction_factor),
+            TraitKind::PredatorHunting => Some(self.hunting_factor),
+            _ => None,
+        }
+    }
+
+    fn state(&self) -> IndividualState {
+        IndividualState {
+            species: CellKind::Predator,
+            age: self.age,
+            hunger: Some(self.hunger),
+            energy: None,
+            reproduction_factor: self.reproduction_factor,
+            moving_factor: self.moving_factor,
+            hunting_factor: Some(self.hunting_factor),
+        }
+    }
+
+    fn save(&self) -> SavedIndividual {
+        SavedIndividual::Predator(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BoundaryMode;
+    use crate::individual::prey::Prey;
+    use crate::individual::with_grid;
+
+    #[test]
+    fn move_wraps_around_the_left_edge() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            ..Default::default()
+        };
+        with_grid(&config, 0.0, |grid, ctx| {
+            let predator = Predator::new(&config);
+            // The predator at (0, 0) chases a prey at (4, 0), one step away across the left edge.
+            let moved = predator.move_to(0, Some((4, 0)), &[1, 4, 5, 20], grid, ctx);
+            assert!(moved);
+            assert!(grid[4].is_predator());
+        });
+    }
+
+    #[test]
+    fn move_does_not_wrap_when_bounded() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            boundary: BoundaryMode::Bounded,
+            ..Default::default()
+        };
+        with_grid(&config, 0.0, |grid, ctx| {
+            let predator = Predator::new(&config);
+            let moved = predator.move_to(0, Some((4, 0)), &[1, 5, 6], grid, ctx);
+            assert!(moved);
+            assert!(grid[1].is_predator());
+        });
+    }
+
+    #[test]
+    fn move_climbs_the_scent_gradient() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            predator_moving_rate: 0.0,
+            targeting: TargetingMode::Scent,
+            ..Default::default()
+        };
+        with_grid(&config, 0.0, |grid, ctx| {
+            // The scent grows towards the right.
+            for idx in 0..25 {
+                grid[idx].scent = grid[idx].x as f32;
+            }
+            let predator = Predator::new(&config);
+            // From (2, 2), the neighbours (1, 2), (2, 1) and (3, 2).
+            let moved = predator.move_to(12, None, &[11, 7, 13], grid, ctx);
+            assert!(moved);
+            assert!(grid[13].is_predator());
+        });
+    }
+
+    #[test]
+    fn pack_mates_raise_the_kill_rate() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            predator_hunting_rate: 0.3,
+            pack_bonus: 0.5,
+            ..Default::default()
+        };
+        // The predator at (2, 2) hunts the prey at (3, 2), with pack mates at (1, 2) and (2, 1).
+        let kills = |pack_mates: &[usize]| {
+            with_grid(&config, 0.0, |grid, ctx| {
+                for &n in pack_mates {
+                    grid[n].place_predator(Box::new(Predator::new(&config)));
+                }
+                (0..2000)
+                    .filter(|_| {
+                        if !grid[13].is_prey() {
+                            grid[13].place_prey(Box::new(Prey::new(&config)));
+                        }
+                        Predator::new(&config).hunt(12, &[11, 7, 13], &mut Vec::new(), grid, ctx)
+                    })
+                    .count()
+            })
+        };
+        let alone = kills(&[]);
+        let in_pack = kills(&[11, 7]);
+        // Kill probabilities of 0.3 alone and 0.6 with two pack mates.
+        assert!((500..700).contains(&alone), "{alone}");
+        assert!((1100..1300).contains(&in_pack), "{in_pack}");
+    }
+
+    #[test]
+    fn gorging_predator_eats_up_to_the_kill_limit() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            predator_hunting_rate: 1.0,
+            max_kills_per_step: 3,
+            ..Default::default()
+        };
+        let neighbours = [6, 7, 8, 11, 13, 16, 17, 18];
+        with_grid(&config, 0.0, |grid, ctx| {
+            for &n in &neighbours {
+                grid[n].place_prey(Box::new(Prey::new(&config)));
+            }
+            let mut empty = Vec::new();
+            assert!(Predator::new(&config).hunt(12, &neighbours, &mut empty, grid, ctx));
+            assert_eq!(empty, [6, 7, 8]);
+            assert_eq!(neighbours.iter().filter(|&&n| grid[n].is_prey()).count(), 5);
+            assert_eq!(ctx.stats.prey_eaten, 3);
+        });
+    }
+
+    #[test]
+    fn nearest_first_hunting_eats_the_closest_prey() {
+        // Prey two cells above (2, 2) at (2, 0) and one cell above at (2, 1), the far one
+        // linked first.
+        let neighbours = [2, 7];
+        for (hunt_order, eaten, spared) in [(HuntOrder::Linked, 2, 7), (HuntOrder::Nearest, 7, 2)] {
+            let config = SimulationConfig {
+                width: 5,
+                height: 5,
+                neighbourhood_radius: 2,
+                predator_hunting_rate: 1.0,
+                hunt_order,
+                ..Default::default()
+            };
+            with_grid(&config, 0.0, |grid, ctx| {
+                for &n in &neighbours {
+                    grid[n].place_prey(Box::new(Prey::new(&config)));
+                }
+                let mut empty = Vec::new();
+                assert!(Predator::new(&config).hunt(12, &neighbours, &mut empty, grid, ctx));
+                assert_eq!(empty, [eaten], "{hunt_order:?}");
+                assert!(grid[spared].is_prey(), "{hunt_order:?}");
+            });
+        }
+    }
+
+    #[test]
+    fn small_meals_take_several_kills_to_sate() {
+        let neighbours = [6, 7, 8, 11, 13, 16, 17, 18];
+        for (nutrition_per_prey, hungers) in [(None, [0, 0, 0]), (Some(3), [15, 12, 9])] {
+            let config = SimulationConfig {
+                width: 5,
+                height: 5,
+                predator_hunting_rate: 1.0,
+                predator_death_after: 20,
+                predator_initial_hunger: Some(18),
+                nutrition_per_prey,
+                ..Default::default()
+            };
+            with_grid(&config, 0.0, |grid, ctx| {
+                for &n in &neighbours {
+                    grid[n].place_prey(Box::new(Prey::new(&config)));
+                }
+                let mut predator = Predator::new(&config);
+                let mut empty = Vec::new();
+                for hunger in hungers {
+                    // A sated predator doesn't hunt.
+                    let sated = predator.hunger() == Some(0);
+                    assert_eq!(predator.hunt(12, &neighbours, &mut empty, grid, ctx), !sated);
+                    assert_eq!(predator.hunger(), Some(hunger), "{nutrition_per_prey:?}");
+                }
+            });
+        }
+    }
+}
@@ -1,68 +1,410 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-use crate::cell::Cell;
-use crate::individual::Individual;
-use rand::prelude::IndexedMutRandom;
+use crate::cell::{Cell, CellKind, GridSlice};
+use crate::config::{PlacementStrategy, ReproductionMode, SimulationConfig};
+use crate::individual::{distance_sq, inherit, move_targets, offset, Context, Individual, SavedIndividual};
+use crate::events::EventKind;
+use crate::stats::{IndividualState, TraitKind};
+use rand::prelude::IndexedRandom;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Prey {
     reproduction_factor: f32,
     moving_factor: f32,
+    energy: u32,
+    age: u32,
+    /// Age at which `unborn` is born.
+    pregnant_until: u32,
+    unborn: Option<Box<Prey>>,
+    #[serde(default)]
+    infected: bool,
+    /// Steps left before recovering, while infected.
+    #[serde(default)]
+    infection_timer: u32,
+    /// Recovered from the disease, which can't be caught twice.
+    #[serde(default)]
+    immune: bool,
 }
 
 impl Prey {
-    pub(crate) fn new(reproduction_factor: f32, moving_factor: f32) -> Self {
+    pub(crate) fn new(config: &SimulationConfig) -> Self {
         Prey {
-            reproduction_factor,
-            moving_factor,
+            reproduction_factor: config.prey_reproduction_rate,
+            moving_factor: config.prey_moving_rate,
+            energy: config.prey_max_energy,
+            age: 0,
+            pregnant_until: 0,
+            unborn: None,
+            infected: false,
+            infection_timer: 0,
+            immune: false,
         }
     }
 
-    fn move_to(&self, local_empty_cells: &mut [Rc<RefCell<Cell>>]) -> bool {
+    fn offspring(&self, partner: Option<&Cell>, ctx: &mut Context) -> Self {
+        let partner_trait = |which| partner.and_then(|cell| cell.trait_value(which));
+        Prey {
+            reproduction_factor: inherit(self.reproduction_factor, partner_trait(TraitKind::PreyReproduction), ctx.config.prey_reproduction_rate, ctx),
+            moving_factor: inherit(self.moving_factor, partner_trait(TraitKind::PreyMoving), ctx.config.prey_moving_rate, ctx),
+            energy: ctx.config.prey_max_energy,
+            age: 0,
+            pregnant_until: 0,
+            unborn: None,
+            infected: false,
+            infection_timer: 0,
+            immune: false,
+        }
+    }
+
+    pub(crate) fn infect(&mut self, config: &SimulationConfig) {
+        self.infected = true;
+        self.infection_timer = config.infection_duration;
+    }
+
+    /// Runs one step of the disease: a healthy prey may catch it from its infected neighbours,
+    /// an infected one may die of it or recover. Returns whether the prey died.
+    fn run_infection(&mut self, local_contents: &[usize], grid: &GridSlice, ctx: &mut Context) -> bool {
+        if self.infected {
+            if ctx.config.infection_death_rate > 0.0 && ctx.rng.random::<f32>() < ctx.config.infection_death_rate {
+                return true
+            }
+            self.infection_timer = self.infection_timer.saturating_sub(1);
+            if self.infection_timer == 0 {
+                self.infected = false;
+                self.immune = true;
+            }
+            return false
+        }
+        let rate = ctx.config.infection_rate;
+        if self.immune || rate <= 0.0 {
+            return false
+        }
+        let contacts = local_contents.iter().filter(|&&n| grid.seen(n).is_infected()).count();
+        if contacts > 0 && ctx.rng.random::<f32>() < 1.0 - (1.0 - rate).powi(contacts as i32) {
+            self.infect(ctx.config);
+            ctx.stats.prey_infected += 1;
+        }
+        false
+    }
+
+    /// Empty neighbour closest to the centre of the neighbouring prey, when the prey follows its
+    /// herd this step.
+    fn herd_cell(idx: usize, local_contents: &[usize], local_empty_cells: &[usize], grid: &GridSlice, ctx: &mut Context) -> Option<usize> {
+        let cohesion = ctx.config.prey_cohesion;
+        let pos = (grid[idx].x, grid[idx].y);
+        let herd = local_contents
+            .iter()
+            .filter(|&&n| grid.seen(n).is_prey())
+            .map(|&n| offset(pos, (grid[n].x, grid[n].y), ctx.config))
+            .collect::<Vec<_>>();
+        if cohesion <= 0.0 || herd.is_empty() || ctx.rng.random::<f32>() >= cohesion {
+            return None
+        }
+        let centre_x = herd.iter().map(|h| h.0 as f32).sum::<f32>() / herd.len() as f32;
+        let centre_y = herd.iter().map(|h| h.1 as f32).sum::<f32>() / herd.len() as f32;
+        let distance_to_centre = |n: usize| {
+            let (dx, dy) = offset(pos, (grid[n].x, grid[n].y), ctx.config);
+            (dx as f32 - centre_x).powi(2) + (dy as f32 - centre_y).powi(2)
+        };
+        local_empty_cells
+            .iter()
+            .copied()
+            .min_by(|&a, &b| distance_to_centre(a).total_cmp(&distance_to_centre(b)))
+    }
+
+    /// Random empty neighbour among those with the most grass, so grazed patches are left behind.
+    fn grazing_cell(local_empty_cells: &[usize], grid: &GridSlice, ctx: &mut Context) -> usize {
+        let most_grass = local_empty_cells.iter().map(|&n| grid.seen(n).grass).fold(f32::MIN, f32::max);
+        let grassiest = local_empty_cells.iter().copied().filter(|&n| grid.seen(n).grass == most_grass).collect::<Vec<_>>();
+        *grassiest.choose(ctx.rng).unwrap()
+    }
+
+    /// Whether overcrowding kills the prey this step, see `crowding_death_rate`.
+    fn is_crowded_out(local_contents: &[usize], grid: &GridSlice, ctx: &mut Context) -> bool {
+        let rate = ctx.config.crowding_death_rate;
+        let crowd = local_contents.iter().filter(|&&n| grid.seen(n).is_prey()).count() as u32;
+        let excess = crowd.saturating_sub(ctx.config.crowding_threshold);
+        rate > 0.0 && excess > 0 && ctx.rng.random::<f32>() < rate * excess as f32
+    }
+
+    /// Empty cell farthest from the predator, if it is farther than the prey already is.
+    fn flee_cell(idx: usize, threat: (i32, i32), local_empty_cells: &[usize], grid: &GridSlice, config: &SimulationConfig) -> Option<usize> {
+        let farthest = *local_empty_cells
+            .iter()
+            .max_by_key(|&&n| distance_sq((grid[n].x, grid[n].y), threat, config))?;
+        let pos = (grid[idx].x, grid[idx].y);
+        (distance_sq((grid[farthest].x, grid[farthest].y), threat, config) > distance_sq(pos, threat, config)).then_some(farthest)
+    }
+
+    fn move_to(&self, idx: usize, threat: Option<(i32, i32)>, local_contents: &[usize], local_empty_cells: &[usize], grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        let local_empty_cells = &*move_targets(idx, local_empty_cells, grid, ctx.config);
+        let flee_cell = threat.map(|threat| Self::flee_cell(idx, threat, local_empty_cells, grid, ctx.config));
+        if flee_cell == Some(None) {
+            ctx.stats.prey_cornered += 1;
+        }
         if local_empty_cells.is_empty() {
+            ctx.stats.blocked_moves += 1;
             return false
         }
-        let mut rng = rand::rng();
-        let rng_nb: f32 = rng.random();
+        let rng_nb: f32 = ctx.rng.random();
         if rng_nb > self.moving_factor {
             return false
         }
-        let empty_cell = local_empty_cells.choose_mut(&mut rng).unwrap();
-        empty_cell.borrow_mut().content = Some(Box::new(self.clone()));
-        empty_cell.borrow_mut().is_empty = false;
-        empty_cell.borrow_mut().is_prey = true;
+        let empty_cell = match flee_cell {
+            // Flee to the empty cell farthest from the predator, or stay if none is safer.
+            Some(Some(n)) => n,
+            Some(None) => return false,
+            None => Self::herd_cell(idx, local_contents, local_empty_cells, grid, ctx)
+                .unwrap_or_else(|| Self::grazing_cell(local_empty_cells, grid, ctx)),
+        };
+        let energy = self.energy.saturating_sub(ctx.config.move_energy_cost);
+        grid[empty_cell].place_prey(Box::new(Prey { energy, ..self.clone() }));
+        ctx.log(EventKind::Move, &grid[empty_cell], CellKind::Prey);
         true
     }
 
-    fn reproduce(&self, local_contents: &[Rc<RefCell<Cell>>], local_empty_cells: &mut [Rc<RefCell<Cell>>]) -> bool {
-        if local_empty_cells.is_empty() {
+    /// Conceives an offspring if not already pregnant, then gives birth once the gestation is over.
+    fn reproduce(&mut self, idx: usize, local_contents: &[usize], local_empty_cells: &[usize], grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        if self.unborn.is_none() && !self.conceive(local_contents, local_empty_cells, grid, ctx) {
             return false
         }
-        let nb_prey = local_contents.iter().filter(|cell| cell.borrow().is_prey()).count();
-        if nb_prey == 0 || nb_prey >= 4 {
+        let grass_threshold = ctx.config.reproduce_grass_threshold;
+        if self.age < self.pregnant_until || local_empty_cells.is_empty() || grid[idx].grass < grass_threshold || !ctx.admit_birth(CellKind::Prey) {
             return false
         }
-        let mut rng = rand::rng();
-        for cell in local_contents {
-            let rng_nb: f32 = rng.random();
-            if cell.borrow().is_prey() && rng_nb < self.reproduction_factor {
-                let empty_cell = local_empty_cells.choose_mut(&mut rng).unwrap();
-                empty_cell.borrow_mut().content = Some(Box::new(Prey::new(self.reproduction_factor, self.moving_factor)));
-                empty_cell.borrow_mut().is_empty = false;
-                empty_cell.borrow_mut().is_prey = true;
-                return true
-            }
+        grid[idx].grass -= grass_threshold;
+        let empty_cell = match ctx.config.birth_placement {
+            PlacementStrategy::Random => *local_empty_cells.choose(ctx.rng).unwrap(),
+            PlacementStrategy::Greedy => Self::grazing_cell(local_empty_cells, grid, ctx),
+        };
+        grid[empty_cell].place_prey(self.unborn.take().unwrap());
+        ctx.stats.prey_born += 1;
+        ctx.log(EventKind::Birth, &grid[empty_cell], CellKind::Prey);
+        true
+    }
+
+    fn conceive(&mut self, local_contents: &[usize], local_empty_cells: &[usize], grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        let energy_cost = ctx.config.prey_energy_cost_reproduce;
+        if local_empty_cells.is_empty() || self.energy <= energy_cost {
+            return false
         }
-        false
+        let nb_prey = local_contents.iter().filter(|&&n| grid.seen(n).is_prey()).count() as u32;
+        let config = ctx.config;
+        if !(config.prey_reproduction_min_neighbours..=config.prey_reproduction_max_neighbours).contains(&nb_prey) {
+            return false
+        }
+        let partner = match ctx.config.reproduction_mode {
+            ReproductionMode::Asexual => {
+                if ctx.rng.random::<f32>() >= self.reproduction_factor * ctx.season_factor {
+                    return false
+                }
+                None
+            }
+            ReproductionMode::Sexual => {
+                let partner = local_contents.iter().copied().find(|&n| {
+                    let rng_nb: f32 = ctx.rng.random();
+                    grid.seen(n).is_prey() && rng_nb < self.reproduction_factor * ctx.season_factor
+                });
+                match partner {
+                    Some(n) => Some(n),
+                    None => return false,
+                }
+            }
+        };
+        let offspring = self.offspring(partner.map(|n| grid.seen(n)), ctx);
+        self.unborn = Some(Box::new(offspring));
+        self.pregnant_until = self.age + ctx.config.prey_gestation;
+        self.energy -= energy_cost;
+        true
     }
 }
 
 impl Individual for Prey {
-    fn update(&mut self, _nearest_prey: Option<(i32, i32)>, local_contents: &mut [Rc<RefCell<Cell>>], local_empty_cells: &mut Vec<Rc<RefCell<Cell>>>) -> bool {
-        if self.reproduce(local_contents, local_empty_cells){
+    fn update(&mut self, idx: usize, nearest_predator: Option<(i32, i32)>, local_contents: &[usize], local_empty_cells: &mut Vec<usize>, grid: &mut GridSlice, ctx: &mut Context) -> bool {
+        self.age += 1;
+        if self.age >= ctx.config.prey_max_age {
+            ctx.stats.deaths_by_old_age += 1;
+            ctx.log(EventKind::Death, &grid[idx], CellKind::Prey);
+            return true
+        }
+        self.energy = self.energy.saturating_sub(1);
+        let grass = &mut grid[idx].grass;
+        if *grass >= ctx.config.graze_amount {
+            *grass -= ctx.config.graze_amount;
+            self.energy = ctx.config.prey_max_energy;
+        }
+        if self.energy == 0 {
+            ctx.log(EventKind::Death, &grid[idx], CellKind::Prey);
+            return true
+        }
+        if self.run_infection(local_contents, grid, ctx) {
+            ctx.stats.prey_died_of_infection += 1;
+            ctx.log(EventKind::Death, &grid[idx], CellKind::Prey);
+            return true
+        }
+        if Self::is_crowded_out(local_contents, grid, ctx) {
+            ctx.stats.prey_crowded += 1;
+            ctx.log(EventKind::Death, &grid[idx], CellKind::Prey);
+            return true
+        }
+        if self.reproduce(idx, local_contents, local_empty_cells, grid, ctx){
             return false
         }
-        self.move_to(local_empty_cells)
+        self.move_to(idx, nearest_predator, local_contents, local_empty_cells, grid, ctx)
+    }
+
+    fn age(&self) -> u32 {
+        self.age
+    }
+
+    fn is_infected(&self) -> bool {
+        self.infected
+    }
+
+    fn trait_value(&self, which: TraitKind) -> Option<f32> {
+        match which {
+            TraitKind::PreyMoving => Some(self.moving_factor),
+            TraitKind::PreyReproduction => Some(self.reproduction_factor),
+            _ => None,
+        }
+    }
+
+    fn state(&self) -> IndividualState {
+        IndividualState {
+            species: CellKind::Prey,
+            age: self.age,
+            hunger: None,
+            energy: Some(self.energy),
+            reproduction_factor: self.reproduction_factor,
+            moving_factor: self.moving_factor,
+            hunting_factor: None,
+        }
     }
-}
\ No newline at end of file
+
+    fn save(&self) -> SavedIndividual {
+        SavedIndividual::Prey(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BoundaryMode;
+    use crate::individual::with_grid;
+
+    #[test]
+    fn move_heads_for_the_herd() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            prey_moving_rate: 1.0,
+            prey_cohesion: 1.0,
+            ..Default::default()
+        };
+        with_grid(&config, 0.0, |grid, ctx| {
+            // The herd of (2, 2) sits at (3, 1) and (3, 3), around (3, 2).
+            grid[8].place_prey(Box::new(Prey::new(&config)));
+            grid[18].place_prey(Box::new(Prey::new(&config)));
+            let prey = Prey::new(&config);
+            let neighbours = [6, 7, 8, 11, 13, 16, 17, 18];
+            let empty = [6, 7, 11, 13, 16, 17];
+            assert!(prey.move_to(12, None, &neighbours, &empty, grid, ctx));
+            assert!(grid[13].is_prey());
+        });
+    }
+
+    #[test]
+    fn move_heads_for_the_grass() {
+        let config = SimulationConfig { width: 5, height: 5, prey_moving_rate: 1.0, ..Default::default() };
+        with_grid(&config, 2.0, |grid, ctx| {
+            grid[17].grass = 5.0;
+            let neighbours = [6, 7, 8, 11, 13, 16, 17, 18];
+            assert!(Prey::new(&config).move_to(12, None, &neighbours, &neighbours, grid, ctx));
+            assert!(grid[17].is_prey());
+        });
+    }
+
+    #[test]
+    fn flee_runs_away_across_the_edge() {
+        let mut config = SimulationConfig { width: 5, height: 5, ..Default::default() };
+        let mut cells = (0..5)
+            .flat_map(|y| (0..5).map(move |x| Cell::new(x, y, 0.0)))
+            .collect::<Vec<_>>();
+        let grid = GridSlice::new(&mut cells, 0, None);
+        // The prey at (0, 2) is next to the predator at (4, 2) across the left edge, so the
+        // way out is to the right, to (1, 2), rather than up to (0, 1).
+        assert_eq!(Prey::flee_cell(10, (4, 2), &[5, 11], &grid, &config), Some(11));
+        config.boundary = BoundaryMode::Bounded;
+        assert_eq!(Prey::flee_cell(10, (4, 2), &[5, 11], &grid, &config), Some(5));
+    }
+
+    #[test]
+    fn crowding_bounds_gate_reproduction() {
+        let mut config = SimulationConfig {
+            width: 5,
+            height: 5,
+            prey_reproduction_rate: 1.0,
+            reproduction_mode: ReproductionMode::Asexual,
+            ..Default::default()
+        };
+        let neighbours = [6, 7, 8, 11, 13, 16, 17, 18];
+        let empty = [11, 13, 16, 17, 18];
+        for (max_neighbours, breeds) in [(2, false), (3, true), (8, true)] {
+            config.prey_reproduction_max_neighbours = max_neighbours;
+            with_grid(&config, 0.0, |grid, ctx| {
+                // Three prey neighbours above the centre.
+                for idx in [6, 7, 8] {
+                    grid[idx].place_prey(Box::new(Prey::new(&config)));
+                }
+                let mut prey = Prey::new(&config);
+                assert_eq!(prey.reproduce(12, &neighbours, &empty, grid, ctx), breeds, "max {max_neighbours}");
+            });
+        }
+        config.prey_reproduction_max_neighbours = 8;
+        config.prey_reproduction_min_neighbours = 4;
+        with_grid(&config, 0.0, |grid, ctx| {
+            assert!(!Prey::new(&config).reproduce(12, &neighbours, &empty, grid, ctx));
+        });
+    }
+
+    #[test]
+    fn greedy_placement_breeds_on_the_grassiest_cell() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            prey_reproduction_rate: 1.0,
+            reproduction_mode: ReproductionMode::Asexual,
+            birth_placement: PlacementStrategy::Greedy,
+            ..Default::default()
+        };
+        with_grid(&config, 2.0, |grid, ctx| {
+            grid[17].grass = 5.0;
+            let neighbours = [6, 7, 8, 11, 13, 16, 17, 18];
+            assert!(Prey::new(&config).reproduce(12, &neighbours, &neighbours, grid, ctx));
+            assert!(grid[17].is_prey());
+            assert_eq!(neighbours.iter().filter(|&&n| grid[n].is_prey()).count(), 1);
+        });
+    }
+
+    #[test]
+    fn prey_on_bare_ground_cannot_breed() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            prey_reproduction_rate: 1.0,
+            reproduction_mode: ReproductionMode::Asexual,
+            reproduce_grass_threshold: 0.5,
+            ..Default::default()
+        };
+        with_grid(&config, 2.0, |grid, ctx| {
+            grid[12].grass = 0.0;
+            let neighbours = [6, 7, 8, 11, 13, 16, 17, 18];
+            let mut prey = Prey::new(&config);
+            assert!(!prey.reproduce(12, &neighbours, &neighbours, grid, ctx));
+            assert!(neighbours.iter().all(|&n| !grid[n].is_prey()));
+            grid[12].grass = 2.0;
+            assert!(prey.reproduce(12, &neighbours, &neighbours, grid, ctx));
+            assert_eq!(grid[12].grass, 1.5);
+        });
+    }
+}
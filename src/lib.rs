@@ -1,16 +1,85 @@
+//! Simulation of a simple ecosystem with prey and predators on a grid of cells.
+//!
+//! # Threading
+//!
+//! The grid is a plain `Vec<Cell>` and each `Cell` owns its individual outright: there is no
+//! `Rc<RefCell>` nor mutex anywhere. A step runs in phases whose rows have disjoint
+//! neighbourhoods, and each phase hands every band of rows to a rayon thread as a
+//! `&mut [Cell]`, see `SimulationConfig::parallel`. That needs `Cell: Send`, and `Sync` lets
+//! the whole grid be read from several threads at once:
+//!
+//! ```
+//! use life_game::cell::Cell;
+//! use life_game::config::SimulationConfig;
+//! use life_game::simulation::{get_cell_ref, Simulation};
+//!
+//! let config = SimulationConfig { width: 12, height: 12, ..Default::default() };
+//! let mut sim = Simulation::with_seed(config, 7).unwrap();
+//! sim.init_grid();
+//! sim.init_simulation();
+//! let mut cells: Vec<Cell> = (0..12)
+//!     .flat_map(|y| (0..12).map(move |x| (x, y)))
+//!     .map(|(x, y)| get_cell_ref(&sim, x, y).unwrap().clone())
+//!     .collect();
+//!
+//! // One band of 3 rows per thread, each with exclusive access to its own cells.
+//! let links: usize = std::thread::scope(|scope| {
+//!     let workers: Vec<_> = cells
+//!         .chunks_mut(3 * 12)
+//!         .map(|band| scope.spawn(move || band.iter().map(|cell| cell.neighbour_positions().len()).sum::<usize>()))
+//!         .collect();
+//!     workers.into_iter().map(|worker| worker.join().unwrap()).sum()
+//! });
+//! assert_eq!(links, 12 * 12 * 8);
+//!
+//! // A whole simulation can move to another thread as well.
+//! let sim = std::thread::spawn(move || {
+//!     sim.update();
+//!     sim
+//! })
+//! .join()
+//! .unwrap();
+//! assert_eq!(sim.step_count(), 1);
+//! ```
+
 #![warn(clippy::all)]
 // This is a Rust library that simulates a simple ecosystem with prey and predators.
 
 mod individual;
 pub mod cell;
+pub mod config;
+pub mod events;
+pub mod geom;
+pub mod run;
 pub mod simulation;
+pub mod spatial;
+pub mod stats;
+pub mod sweep;
 
+use crate::config::{BoundaryMode, HuntOrder, InheritanceMode, InitialLayout, Neighbourhood, PlacementStrategy, PredatorMortality, PreyIndex, ReproductionMode, SimulationConfig, TargetingMode, Topology, UpdateMode};
+use crate::run::SimulationStepper;
 use crate::simulation::Simulation;
+use crate::stats::TraitKind;
 use pyo3::prelude::*;
 
 #[pymodule(name = "life_game")]
 fn pp_sim(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
+    m.add_class::<SimulationConfig>()?;
+    m.add_class::<Neighbourhood>()?;
+    m.add_class::<Topology>()?;
+    m.add_class::<BoundaryMode>()?;
+    m.add_class::<ReproductionMode>()?;
+    m.add_class::<PlacementStrategy>()?;
+    m.add_class::<InheritanceMode>()?;
+    m.add_class::<InitialLayout>()?;
+    m.add_class::<TargetingMode>()?;
+    m.add_class::<PredatorMortality>()?;
+    m.add_class::<HuntOrder>()?;
+    m.add_class::<PreyIndex>()?;
+    m.add_class::<UpdateMode>()?;
+    m.add_class::<TraitKind>()?;
     m.add_class::<Simulation>()?;
+    m.add_class::<SimulationStepper>()?;
     Ok(())
 }
 
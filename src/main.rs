@@ -1,19 +1,13 @@
+use life_game::config::SimulationConfig;
 use life_game::simulation::Simulation;
 
 fn main() {
-    let mut simulation = Simulation::new(
-        99,
-        99,
-        0.5,
-        0.5,
-        0.5,
-        0.5,
-        0.5,
-        0.1,
-        25,
-        1500,
-        1000
-    );
+    let mut simulation = Simulation::new(SimulationConfig {
+        width: 99,
+        height: 99,
+        ..Default::default()
+    })
+    .expect("valid config");
     simulation.init_grid();
     simulation.init_simulation();
     for i in 0..10000 {
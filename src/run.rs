@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+
+use pyo3::{pyclass, pymethods, Py, PyRef, Python};
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::Simulation;
+
+/// When `Simulation::run_until` should stop before reaching its step limit.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StopCondition {
+    PredatorsExtinct,
+    PreyExtinct,
+    AllExtinct,
+    /// Both populations varied by at most `tolerance` individuals over the last `window` steps.
+    Stable { window: usize, tolerance: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    ConditionMet,
+    MaxSteps,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunOutcome {
+    /// Number of steps run before stopping.
+    pub step: usize,
+    pub reason: StopReason,
+}
+
+impl StopCondition {
+    /// Checks the condition against the `(nb_prey, nb_predators)` counts seen so far, oldest first.
+    pub(crate) fn is_met(&self, counts: &VecDeque<(usize, usize)>) -> bool {
+        let Some(&(nb_prey, nb_predators)) = counts.back() else {
+            return false;
+        };
+        match *self {
+            StopCondition::PredatorsExtinct => nb_predators == 0,
+            StopCondition::PreyExtinct => nb_prey == 0,
+            StopCondition::AllExtinct => nb_prey == 0 && nb_predators == 0,
+            StopCondition::Stable { window, tolerance } => {
+                if counts.len() <= window {
+                    return false;
+                }
+                let recent = counts.iter().skip(counts.len() - window - 1);
+                let range = |values: Vec<usize>| {
+                    values.iter().max().unwrap() - values.iter().min().unwrap()
+                };
+                range(recent.clone().map(|c| c.0).collect()) <= tolerance
+                    && range(recent.map(|c| c.1).collect()) <= tolerance
+            }
+        }
+    }
+
+    /// Number of past counts `is_met` needs to look at.
+    pub(crate) fn memory(&self) -> usize {
+        match *self {
+            StopCondition::Stable { window, .. } => window + 1,
+            _ => 1,
+        }
+    }
+}
+
+/// Python iterator over the steps of a simulation, see `Simulation::iter_steps`.
+#[pyclass(module = "life_game")]
+pub struct SimulationStepper {
+    sim: Py<Simulation>,
+    remaining: usize,
+}
+
+impl SimulationStepper {
+    pub(crate) fn new(sim: Py<Simulation>, steps: usize) -> Self {
+        SimulationStepper { sim, remaining: steps }
+    }
+}
+
+#[pymethods]
+impl SimulationStepper {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Runs the next step, `None` raising `StopIteration` once the steps are used up or both
+    /// species are extinct.
+    fn __next__(&mut self, py: Python<'_>) -> Option<(usize, usize)> {
+        let mut sim = self.sim.borrow_mut(py);
+        if self.remaining == 0 || sim.count_individuals() == (0, 0) {
+            return None;
+        }
+        self.remaining -= 1;
+        sim.update();
+        Some(sim.count_individuals())
+    }
+}
@@ -1,136 +1,1594 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-use crate::cell::Cell;
+use crate::cell::{Cell, CellKind, GridSlice};
+use crate::config::{check_rate, STRIPE_WIDTH, BoundaryMode, ConfigError, HuntOrder, InheritanceMode, InitialLayout, Neighbourhood, PlacementStrategy, PredatorMortality, PreyIndex, ReproductionMode, SimulationConfig, TargetingMode, Topology, UpdateMode};
+use crate::events::{Event, EventKind};
+use crate::geom::{hex_distance, wrap};
+use crate::individual::{offset, Census, Context, SavedIndividual};
+use crate::individual::omnivore::Omnivore;
 use crate::individual::predator::Predator;
 use crate::individual::prey::Prey;
+use crate::run::{RunOutcome, SimulationStepper, StopCondition, StopReason};
+use crate::spatial::{GridIndex, KdTreeIndex, Space, SpatialIndex, GRID_BUCKET_SIZE};
+use crate::stats::{IndividualState, Rates, SimSummary, StepStats, TraitKind};
 use kd_tree::KdTree;
-use pyo3::{pyclass, pymethods};
-use rand::Rng;
+use numpy::PyArray2;
+use image::{Rgb, RgbImage};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::types::PyBytes;
+use pyo3::{pyclass, pymethods, Bound, PyObject, PyResult, Python};
+use rand::rngs::StdRng;
+use rand_chacha::ChaCha12Rng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::iter::StepBy;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 
-pub fn get_cell(sim: &mut Simulation, x: i32, y: i32) -> Option<Rc<RefCell<Cell>>> {
+/// `(x, y)` coordinates of a cell.
+pub type Pos = (i32, i32);
+
+/// A predator position and the position of the prey it chases, if any.
+pub type PredatorTarget = (Pos, Option<Pos>);
+
+/// Largest number of past steps `Simulation::measured_rates` can average over.
+pub const RATE_WINDOW_MAX: usize = 1000;
+
+/// Events of a step along with the `(nb_prey, nb_predators)` at its start and at its end.
+type StepRecord = (StepStats, (usize, usize), (usize, usize));
+
+/// Individuals of one grid row to update during a phase, with their nearest target.
+struct RowJob {
+    y: i32,
+    prey: Vec<(usize, Option<(i32, i32)>)>,
+    predators: Vec<(usize, Option<(i32, i32)>)>,
+    omnivores: Vec<(usize, Option<(i32, i32)>)>,
+}
+
+impl RowJob {
+    /// Runs the job on `grid`, which must contain the rows surrounding `y`.
+    ///
+    /// The RNG only depends on the phase seed and the row, so the outcome doesn't depend on
+    /// which thread runs the job or in which order. Returns the events of the row too, if
+    /// `log_events` is set.
+    fn run(&self, grid: &mut GridSlice, config: &SimulationConfig, phase_seed: u64, season_factor: f32, census: &Census, log_events: bool) -> (StepStats, Vec<Event>) {
+        let mut rng = StdRng::seed_from_u64(phase_seed.wrapping_add(self.y as u64));
+        let mut ctx = Context {
+            config,
+            rng: &mut rng,
+            stats: StepStats::default(),
+            season_factor,
+            census,
+            events: log_events.then(Vec::new),
+        };
+        for &(idx, nearest_predator) in &self.prey {
+            Cell::update(grid, idx, nearest_predator, &mut ctx);
+        }
+        for &(idx, nearest_prey) in &self.predators {
+            Cell::update(grid, idx, nearest_prey, &mut ctx);
+        }
+        for &(idx, nearest_prey) in &self.omnivores {
+            Cell::update(grid, idx, nearest_prey, &mut ctx);
+        }
+        (ctx.stats, ctx.events.unwrap_or_default())
+    }
+}
+
+/// KdTree over `positions`; the tree needs array points.
+fn build_tree(positions: &[Pos]) -> KdTree<[i32; 2]> {
+    KdTree::build(positions.iter().map(|&(x, y)| [x, y]).collect())
+}
+
+/// 64-bit FNV-1a over the bytes written to it: unlike `DefaultHasher`, the same bytes give the
+/// same hash across runs, builds and platforms.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Write for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Rows, or columns, updated by the phases with offset `offset`: the `step` offsets split
+/// `0..len` between them, so the phases of a step visit every cell exactly once.
+fn phase_lines(len: i32, offset: i32, step: i32) -> StepBy<Range<i32>> {
+    (offset..len).step_by(step as usize)
+}
+
+pub fn get_cell(sim: &mut Simulation, x: i32, y: i32) -> Option<&mut Cell> {
+    if x < 0 || x >= sim.width || y < 0 || y >= sim.height {
+        return None;
+    }
+    let idx = sim.index(x, y);
+    sim.grid.get_mut(idx)
+}
+
+/// Same as `get_cell` for read-only peeks, which don't need the simulation mutably.
+pub fn get_cell_ref(sim: &Simulation, x: i32, y: i32) -> Option<&Cell> {
     if x < 0 || x >= sim.width || y < 0 || y >= sim.height {
         return None;
     }
-    let cell = Rc::clone(&sim.grid[x as usize][y as usize]);
-    Some(cell)
+    sim.grid.get(sim.index(x, y))
+}
+
+/// Owned copy of the grid of a `Simulation`, see `Simulation::snapshot`. It shares nothing with
+/// the simulation it was taken from, so either can change without affecting the other.
+#[derive(Clone)]
+pub struct SimulationSnapshot {
+    pub width: i32,
+    pub height: i32,
+    pub step: usize,
+    /// Row-major content of the cells.
+    pub cells: Vec<CellKind>,
+    /// Row-major grass of the cells.
+    pub grass: Vec<f32>,
+    scent: Vec<f32>,
+    individuals: Vec<Option<SavedIndividual>>,
+    rng: ChaCha12Rng,
 }
 
 
-#[pyclass(unsendable)]
+#[pyclass(unsendable, module = "life_game")]
+#[derive(Serialize, Deserialize)]
 pub struct Simulation {
     width: i32,
     height: i32,
-    grid: Vec<Vec<Rc<RefCell<Cell>>>>,
-    prey_position: Vec<[i32; 2]>,
-    predator_position: Vec<(i32, i32)>,
-    prey_reproduction_factor: f32,
-    prey_moving_factor: f32,
-    predator_reproduction_factor: f32,
-    predator_moving_factor: f32,
-    predator_hunting_factor: f32,
-    predator_death_rate: f32,
-    predator_max_hunger: u32,
-    nb_initial_prey: u32,
-    nb_initial_predators: u32,
-    kd_tree: Option<KdTree<[i32; 2]>>,
+    /// Row-major cells, see `index`.
+    grid: Vec<Cell>,
+    prey_position: Vec<Pos>,
+    predator_position: Vec<Pos>,
+    config: SimulationConfig,
+    /// Brought up to date at the start of every sweep, so not worth saving. Tagged with the
+    /// `prey_index` it was built for.
+    #[serde(skip)]
+    prey_index: Option<(PreyIndex, Box<dyn SpatialIndex + Send + Sync>)>,
+    /// Positions `prey_index` holds.
+    #[serde(skip)]
+    indexed_prey: Vec<Pos>,
+    /// Rebuilt at the start of every sweep.
+    #[serde(skip)]
+    predator_kd_tree: Option<KdTree<[i32; 2]>>,
+    seed: u64,
+    /// The generator behind `StdRng`, used directly because its state can be saved.
+    rng: ChaCha12Rng,
+    /// Events of the last step.
+    stats: StepStats,
+    /// Number of steps run so far.
+    step: usize,
+    /// `(step, nb_prey, nb_predators)` after each step, when `config.record_history` is set.
+    history: Vec<(usize, usize, usize)>,
+    /// The last `RATE_WINDOW_MAX` steps, see `measured_rates`.
+    #[serde(default)]
+    recent_steps: VecDeque<StepRecord>,
+    stop_condition: Option<StopCondition>,
+    /// Largest number of events kept, 0 when the event log is off.
+    event_log_cap: usize,
+    events: VecDeque<Event>,
 }
 
 
+/// Deep copy that can be run on its own without affecting the original, and that steps exactly
+/// like it. The indexes are left out, they are rebuilt at the next sweep.
+impl Clone for Simulation {
+    fn clone(&self) -> Self {
+        Simulation {
+            width: self.width,
+            height: self.height,
+            grid: self.grid.clone(),
+            prey_position: self.prey_position.clone(),
+            predator_position: self.predator_position.clone(),
+            config: self.config.clone(),
+            prey_index: None,
+            indexed_prey: Vec::new(),
+            predator_kd_tree: None,
+            seed: self.seed,
+            rng: self.rng.clone(),
+            stats: self.stats,
+            step: self.step,
+            history: self.history.clone(),
+            recent_steps: self.recent_steps.clone(),
+            stop_condition: self.stop_condition,
+            event_log_cap: self.event_log_cap,
+            events: self.events.clone(),
+        }
+    }
+}
+
+impl Simulation {
+    /// Runs up to `max_steps` steps, stopping early as soon as `stop` is met.
+    ///
+    /// The condition is checked before the first step too, so an already extinct population
+    /// stops at step 0.
+    pub fn run_until(&mut self, max_steps: usize, stop: StopCondition) -> RunOutcome {
+        self.run_steps(max_steps, Some(stop)).0
+    }
+
+    /// Copies the grid, individuals included, along with the step counter and the random
+    /// generator, so that `restore` brings the simulation back to this very state.
+    pub fn snapshot(&self) -> SimulationSnapshot {
+        SimulationSnapshot {
+            width: self.width,
+            height: self.height,
+            step: self.step,
+            cells: self.grid.iter().map(Cell::kind).collect(),
+            grass: self.grid.iter().map(|cell| cell.grass).collect(),
+            scent: self.grid.iter().map(|cell| cell.scent).collect(),
+            individuals: self.grid.iter().map(|cell| cell.content.as_ref().map(|individual| individual.save())).collect(),
+            rng: self.rng.clone(),
+        }
+    }
+
+    /// Brings the grid, the step counter and the random generator back to `snapshot`. The
+    /// config, history and event log are left as they are. Returns `false`, changing nothing,
+    /// when the snapshot was taken on a grid of another size.
+    pub fn restore(&mut self, snapshot: &SimulationSnapshot) -> bool {
+        if snapshot.width != self.width || snapshot.height != self.height {
+            return false;
+        }
+        for (idx, cell) in self.grid.iter_mut().enumerate() {
+            cell.empty();
+            cell.is_obstacle = snapshot.cells[idx] == CellKind::Obstacle;
+            cell.grass = snapshot.grass[idx];
+            cell.scent = snapshot.scent[idx];
+            if let Some(individual) = snapshot.individuals[idx].clone() {
+                let individual = individual.into_individual();
+                match snapshot.cells[idx] {
+                    CellKind::Prey => cell.place_prey(individual),
+                    CellKind::Predator => cell.place_predator(individual),
+                    _ => cell.place_omnivore(individual),
+                }
+            }
+        }
+        self.collect_positions();
+        self.prey_index = None;
+        self.predator_kd_tree = None;
+        self.step = snapshot.step;
+        self.rng = snapshot.rng.clone();
+        true
+    }
+
+    /// Condition on which `step_n` returns early, if any.
+    pub fn set_stop_condition(&mut self, stop: Option<StopCondition>) {
+        self.stop_condition = stop;
+    }
+
+    /// Runs up to `max_steps` steps, stopping early when `stop` is met, and sums their events.
+    fn run_steps(&mut self, max_steps: usize, stop: Option<StopCondition>) -> (RunOutcome, StepStats) {
+        let mut total = StepStats::default();
+        let mut counts = VecDeque::new();
+        for step in 0..=max_steps {
+            if step > 0 {
+                total += self.update();
+            }
+            if let Some(stop) = stop {
+                counts.push_back(self.count_individuals());
+                if counts.len() > stop.memory() {
+                    counts.pop_front();
+                }
+                if stop.is_met(&counts) {
+                    return (RunOutcome { step, reason: StopReason::ConditionMet }, total);
+                }
+            }
+        }
+        (RunOutcome { step: max_steps, reason: StopReason::MaxSteps }, total)
+    }
+
+    /// Indices of the distinct cells within `r` of `(x, y)`, the cell itself excluded.
+    fn cells_within(&self, x: i32, y: i32, r: i32) -> Vec<usize> {
+        let bounded = self.config.boundary == BoundaryMode::Bounded;
+        let mut cells = self
+            .offsets_within(r, y)
+            .into_iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|&(nx, ny)| !bounded || (nx >= 0 && nx < self.width && ny >= 0 && ny < self.height))
+            .map(|(nx, ny)| self.index(wrap(nx, self.width), wrap(ny, self.height)))
+            .filter(|&idx| idx != self.index(x, y))
+            .collect::<Vec<_>>();
+        // Radii wider than the torus wrap several offsets onto the same cell.
+        cells.sort_unstable();
+        cells.dedup();
+        cells
+    }
+
+    /// Runs whole steps until `budget` has elapsed and returns how many were run. The clock is
+    /// checked between steps, so the last step may end after the budget.
+    pub fn run_for(&mut self, budget: Duration) -> usize {
+        let start = Instant::now();
+        let mut steps = 0;
+        while start.elapsed() < budget {
+            self.update();
+            steps += 1;
+        }
+        steps
+    }
+
+    /// Offsets of the neighbours of a cell of row `y`, see `neighbourhood_radius`.
+    fn neighbour_offsets(&self, y: i32) -> Vec<(i32, i32)> {
+        self.offsets_within(self.config.neighbourhood_radius, y)
+    }
+
+    /// Offsets of the cells within `r` of a cell of row `y` in the neighbourhood shape of the
+    /// config, the cell itself excluded. Only hex grids depend on the row.
+    fn offsets_within(&self, r: i32, y: i32) -> Vec<(i32, i32)> {
+        let neighbourhood = self.config.neighbourhood;
+        let topology = self.config.topology;
+        (-r..=r)
+            .flat_map(|dx| (-r..=r).map(move |dy| (dx, dy)))
+            .filter(|&offset| offset != (0, 0))
+            .filter(|&(dx, dy)| match topology {
+                Topology::Square => neighbourhood == Neighbourhood::Moore || dx.abs() + dy.abs() <= r,
+                Topology::Hex => hex_distance((0, y), (dx, y + dy)) <= r,
+            })
+            .collect()
+    }
+
+    /// Width of the rows and columns strips of a phase: cells that far apart have disjoint
+    /// neighbourhoods and can be updated independently.
+    fn phase_step(&self) -> i32 {
+        2 * self.config.neighbourhood_radius + 1
+    }
+
+    /// Number of prey and predators currently on the grid.
+    pub(crate) fn count_individuals(&self) -> (usize, usize) {
+        self.grid.iter().fold((0, 0), |(prey, predators), cell| {
+            (prey + cell.is_prey() as usize, predators + cell.is_predator() as usize)
+        })
+    }
+
+    /// Moves each predator with no prey around it, with probability `migration_rate`, to a random
+    /// empty cell within `neighbourhood_radius` of the prey centroid. Returns whether any moved.
+    fn migrate_predators(&mut self) -> bool {
+        if self.config.migration_rate <= 0.0 || self.prey_position.is_empty() {
+            return false;
+        }
+        let nb_prey = self.prey_position.len() as f32;
+        let centre_x = (self.prey_position.iter().map(|p| p.0 as f32).sum::<f32>() / nb_prey).round() as i32;
+        let centre_y = (self.prey_position.iter().map(|p| p.1 as f32).sum::<f32>() / nb_prey).round() as i32;
+        let r = self.config.neighbourhood_radius;
+        let mut migrated = false;
+        for (x, y) in self.predator_position.clone() {
+            let idx = self.index(x, y);
+            if self.grid[idx].neighbours().iter().any(|&n| self.grid[n].is_prey())
+                || self.rng.random::<f32>() >= self.config.migration_rate
+            {
+                continue;
+            }
+            let landing_cells = (centre_y - r..=centre_y + r)
+                .flat_map(|y| (centre_x - r..=centre_x + r).map(move |x| (x, y)))
+                .filter(|&(x, y)| x >= 0 && x < self.width && y >= 0 && y < self.height)
+                .map(|(x, y)| self.index(x, y))
+                .filter(|&n| self.grid[n].is_empty())
+                .collect::<Vec<_>>();
+            if let Some(&n) = landing_cells.choose(&mut self.rng) {
+                let predator = self.grid[idx].content.take().expect("predator cells hold a predator");
+                self.grid[idx].empty();
+                self.grid[n].place_predator(predator);
+                let (x, y) = (self.grid[n].x, self.grid[n].y);
+                self.record_events(vec![Event { step: 0, kind: EventKind::Move, x, y, species: CellKind::Predator }]);
+                migrated = true;
+            }
+        }
+        migrated
+    }
+
+    /// Rebuilds the position lists from the current content of the grid.
+    fn collect_positions(&mut self) {
+        self.prey_position.clear();
+        self.predator_position.clear();
+        for cell in &self.grid {
+            if cell.is_prey() {
+                self.prey_position.push((cell.x, cell.y));
+            } else if cell.is_predator() {
+                self.predator_position.push((cell.x, cell.y));
+            }
+        }
+    }
+
+    /// Removes from the shuffled `free_cells` the `count` cells, or as many as there are, the
+    /// `initial_layout` prefers for `species`, in placement order.
+    fn take_initial_cells(&self, free_cells: &mut Vec<usize>, species: CellKind, count: u32) -> Vec<usize> {
+        let (width, height) = (self.width, self.height);
+        let distance_sq = |idx: usize, (cx, cy): (i32, i32)| {
+            let (dx, dy) = (self.grid[idx].x - cx, self.grid[idx].y - cy);
+            dx * dx + dy * dy
+        };
+        // Stable sorts keep the shuffled order among equally preferred cells.
+        match self.config.initial_layout {
+            InitialLayout::Random => {}
+            InitialLayout::CentralCluster => free_cells.sort_by_key(|&idx| distance_sq(idx, (width / 2, height / 2))),
+            InitialLayout::Stripes => free_cells.sort_by_key(|&idx| {
+                let band = self.grid[idx].x / STRIPE_WIDTH % 2;
+                if species == CellKind::Prey { band } else { 1 - band }
+            }),
+            InitialLayout::Colonies => free_cells.sort_by_key(|&idx| {
+                distance_sq(idx, (width / 4, height / 2)).min(distance_sq(idx, (width - 1 - width / 4, height / 2)))
+            }),
+        }
+        let count = (count as usize).min(free_cells.len());
+        free_cells.drain(..count).collect()
+    }
+
+    #[inline]
+    fn index(&self, x: i32, y: i32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    /// Updates the cells `(x, y)` with `x % step == i` and `y % step == j`, see `phase_step`.
+    /// Individuals that arrived in a cell earlier in the step are left alone.
+    ///
+    /// Rows `step` apart have disjoint neighbourhoods, so every row not touching the top or
+    /// bottom edge is updated concurrently on its own `step`-row band of the grid. The edge
+    /// rows, whose neighbourhood wraps around the torus, are updated afterwards.
+    ///
+    /// The outcome is the same as updating the rows one by one from the top: within a row prey
+    /// go first, then predators, then omnivores, each from left to right, and each row draws
+    /// from its own generator. Rows only share the census, which population caps write to. An
+    /// individual gives birth at most once per update, so when the caps have room for one
+    /// birth per individual of the phase no birth can be refused, whatever order the rows run
+    /// in. Otherwise the rows run one after the other, and the last places under a cap go to
+    /// the first rows.
+    ///
+    /// `previous` is the grid as it was at the start of the step in `UpdateMode::Synchronous`.
+    fn update_parallel(&mut self, i: i32, j: i32, census: &Census, previous: Option<&[Cell]>) {
+        let width = self.get_width();
+        let height = self.get_height();
+        let mut jobs = Vec::new();
+        let step = self.phase_step();
+        let r = self.config.neighbourhood_radius;
+
+        for y in phase_lines(height, j, step) {
+            let mut prey_cell = Vec::new();
+            let mut row_prey_coords = Vec::new();
+            let mut predator_cell = Vec::new();
+            let mut row_predator_coords = Vec::new();
+            let mut omnivore_cell = Vec::new();
+            let mut row_omnivore_coords = Vec::new();
+            for x in phase_lines(width, i, step) {
+                let idx = self.index(x, y);
+                let cell = get_cell_ref(self, x, y).expect("phase lines stay on the grid");
+                if cell.arrived {
+                    continue;
+                } else if cell.is_prey() {
+                    row_prey_coords.push((x, y));
+                    prey_cell.push(idx);
+                } else if cell.is_predator() {
+                    row_predator_coords.push((x, y));
+                    predator_cell.push(idx);
+                } else if cell.is_omnivore() {
+                    row_omnivore_coords.push((x, y));
+                    omnivore_cell.push(idx);
+                }
+            }
+            let nearest_prey = match self.config.targeting {
+                TargetingMode::KdTree => self.get_nearest_preys(&row_predator_coords),
+                TargetingMode::Scent => vec![None; row_predator_coords.len()],
+            };
+            let omnivore_prey = self.get_nearest_preys(&row_omnivore_coords);
+            let nearest_predators = self.get_nearest_predators(&row_prey_coords);
+            jobs.push(RowJob {
+                y,
+                prey: prey_cell.into_iter().zip(nearest_predators).collect(),
+                predators: predator_cell.into_iter().zip(nearest_prey).collect(),
+                omnivores: omnivore_cell.into_iter().zip(omnivore_prey).collect(),
+            });
+        }
+
+        let phase_seed: u64 = self.rng.random();
+        let season_factor = self.current_season_factor();
+        let config = &self.config;
+        let log_events = self.event_log_cap > 0;
+        let mut events = Vec::new();
+        let (interior, edges): (Vec<RowJob>, Vec<RowJob>) = jobs
+            .into_iter()
+            .partition(|job| job.y >= r && job.y + r < height);
+        if let (Some(first), Some(last)) = (interior.first(), interior.last()) {
+            let band_len = step as usize * width as usize;
+            let start = (first.y - r) as usize * width as usize;
+            let end = (last.y + r + 1) as usize * width as usize;
+            let run_band = |(band_idx, (band, job)): (usize, (&mut [Cell], &RowJob))| {
+                let mut grid = GridSlice::new(band, start + band_idx * band_len, previous);
+                job.run(&mut grid, config, phase_seed, season_factor, census, log_events)
+            };
+            let births_fit = census.has_room_for(
+                config,
+                interior.iter().map(|job| job.prey.len()).sum(),
+                interior.iter().map(|job| job.predators.len()).sum(),
+                interior.iter().map(|job| job.omnivores.len()).sum(),
+            );
+            let band_results: Vec<(StepStats, Vec<Event>)> = if config.parallel && births_fit {
+                self.grid[start..end]
+                    .par_chunks_mut(band_len)
+                    .zip(interior.par_iter())
+                    .enumerate()
+                    .map(run_band)
+                    .collect()
+            } else {
+                self.grid[start..end]
+                    .chunks_mut(band_len)
+                    .zip(interior.iter())
+                    .enumerate()
+                    .map(run_band)
+                    .collect()
+            };
+            for (stats, band_events) in band_results {
+                self.stats += stats;
+                events.extend(band_events);
+            }
+        }
+        let mut grid = GridSlice::new(&mut self.grid, 0, previous);
+        for job in &edges {
+            let (stats, row_events) = job.run(&mut grid, config, phase_seed, season_factor, census, log_events);
+            self.stats += stats;
+            events.extend(row_events);
+        }
+        self.record_events(events);
+    }
+
+    /// Adds the events of the running step to the log, dropping the oldest ones beyond
+    /// `event_log_cap`.
+    fn record_events(&mut self, events: Vec<Event>) {
+        if self.event_log_cap == 0 {
+            return;
+        }
+        for event in events {
+            if self.events.len() == self.event_log_cap {
+                self.events.pop_front();
+            }
+            self.events.push_back(Event { step: self.step + 1, ..event });
+        }
+    }
+
+    /// Nearest prey of each predator, in the same order as `predator_pos`.
+    #[inline]
+    fn get_nearest_preys(&self, predator_pos: &[Pos]) -> Vec<Option<Pos>> {
+        let radius = self.config.predator_vision_radius;
+        // `build_kd_tree` runs before every sweep and every query.
+        debug_assert!(self.prey_index.is_some(), "prey index not built yet");
+        let Some((_, ref index)) = self.prey_index else {
+            return vec![None; predator_pos.len()];
+        };
+        predator_pos
+            .iter()
+            .map(|&(x, y)| {
+                index
+                    .nearest((x, y))
+                    .filter(|&prey| {
+                        let (dx, dy) = offset((x, y), prey, &self.config);
+                        dx.abs().max(dy.abs()) <= radius
+                    })
+            })
+            .collect()
+    }
+
+    /// Nearest predator of each prey, or `None` when it is farther than `prey_flee_radius`.
+    fn get_nearest_predators(&self, prey_pos: &[Pos]) -> Vec<Option<Pos>> {
+        let radius = self.config.prey_flee_radius;
+        match self.predator_kd_tree {
+            Some(ref kd_tree) if radius > 0 => prey_pos
+                .iter()
+                .map(|&(x, y)| {
+                    kd_tree
+                        .nearest(&[x, y])
+                        .filter(|nearest| nearest.squared_distance <= radius * radius)
+                        .map(|nearest| (nearest.item[0], nearest.item[1]))
+                })
+                .collect(),
+            _ => vec![None; prey_pos.len()],
+        }
+    }
+}
+
 #[pymethods]
 impl Simulation {
+    /// Fails if the config doesn't pass `SimulationConfig::validate`.
+    ///
+    /// The config defaults to `SimulationConfig::default()` so that `pickle` can create the
+    /// simulation before restoring its state.
     #[new]
-    pub fn new(width: i32, height: i32, prey_reproduction_factor: f32, prey_moving_factor: f32, predator_reproduction_factor: f32, predator_moving_factor: f32, predator_hunting_factor: f32, predator_death_rate: f32, predator_max_hunger: u32, nb_initial_prey: u32, nb_initial_predators: u32) -> Self {
-        Simulation {
-            width,
-            height,
+    #[pyo3(signature = (config = SimulationConfig::default()))]
+    pub fn new(config: SimulationConfig) -> Result<Self, ConfigError> {
+        Self::with_seed(config, rand::random())
+    }
+
+    /// Same as `new` but with a fixed seed, so two simulations built with the same
+    /// config and seed evolve identically.
+    #[staticmethod]
+    pub fn with_seed(config: SimulationConfig, seed: u64) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(Simulation {
+            width: config.width,
+            height: config.height,
             grid: Vec::new(),
             prey_position: Vec::new(),
             predator_position: Vec::new(),
-            prey_reproduction_factor,
-            prey_moving_factor,
-            predator_reproduction_factor,
-            predator_moving_factor,
-            predator_hunting_factor,
-            predator_death_rate,
-            predator_max_hunger,
-            nb_initial_prey,
-            nb_initial_predators,
-            kd_tree: None,
-        }
+            config,
+            prey_index: None,
+            indexed_prey: Vec::new(),
+            predator_kd_tree: None,
+            seed,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            stats: StepStats::default(),
+            step: 0,
+            history: Vec::new(),
+            recent_steps: VecDeque::new(),
+            stop_condition: None,
+            event_log_cap: 0,
+            events: VecDeque::new(),
+        })
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
     }
 
     pub fn init_grid(&mut self){
-        for i in 0..self.get_width() {
-            let mut row = Vec::new();
-            for j in 0..self.get_height() {
-                row.push(Rc::new(RefCell::new(Cell::new(i, j))));
+        self.grid.reserve((self.get_width() * self.get_height()) as usize);
+        for j in 0..self.get_height() {
+            for i in 0..self.get_width() {
+                self.grid.push(Cell::new(i, j, self.config.grass_max));
             }
-            self.grid.push(row);
         }
     }
 
 
-    fn get_width(&self) -> i32 {
+    /// Steps run since the start, the last reset or the last clear.
+    #[getter]
+    pub fn step_count(&self) -> usize {
+        self.step
+    }
+
+
+    // Python reads every config parameter back as a property of the simulation, e.g.
+    // `sim.width`. Only the rates below can be changed between steps.
+
+    #[getter]
+    pub fn get_width(&self) -> i32 {
         self.width
     }
 
-    fn get_height(&self) -> i32 {
+    #[getter]
+    pub fn get_height(&self) -> i32 {
         self.height
     }
 
+    #[getter]
+    pub fn predator_death_after(&self) -> u32 {
+        self.config.predator_death_after
+    }
+
+    #[getter]
+    pub fn hunger_increment(&self) -> u32 {
+        self.config.hunger_increment
+    }
+
+    #[getter]
+    pub fn nutrition_per_prey(&self) -> Option<u32> {
+        self.config.nutrition_per_prey
+    }
+
+    #[getter]
+    pub fn predator_initial_hunger(&self) -> Option<u32> {
+        self.config.predator_initial_hunger
+    }
+
+    #[getter]
+    pub fn predator_reproduce_below_hunger(&self) -> Option<u32> {
+        self.config.predator_reproduce_below_hunger
+    }
+
+    #[getter]
+    pub fn pack_bonus(&self) -> f32 {
+        self.config.pack_bonus
+    }
+
+    #[getter]
+    pub fn max_kills_per_step(&self) -> u32 {
+        self.config.max_kills_per_step
+    }
+
+    #[getter]
+    pub fn hunt_order(&self) -> HuntOrder {
+        self.config.hunt_order
+    }
+
+    #[getter]
+    pub fn nb_prey_init(&self) -> u32 {
+        self.config.nb_prey_init
+    }
+
+    #[getter]
+    pub fn initial_layout(&self) -> InitialLayout {
+        self.config.initial_layout
+    }
+
+    #[getter]
+    pub fn nb_predator_init(&self) -> u32 {
+        self.config.nb_predator_init
+    }
+
+    #[getter]
+    pub fn nb_omnivore_init(&self) -> u32 {
+        self.config.nb_omnivore_init
+    }
+
+    #[getter]
+    pub fn omnivore_death_after(&self) -> u32 {
+        self.config.omnivore_death_after
+    }
+
+    #[getter]
+    pub fn omnivore_grass_gain(&self) -> u32 {
+        self.config.omnivore_grass_gain
+    }
+
+    #[getter]
+    pub fn omnivore_max_age(&self) -> u32 {
+        self.config.omnivore_max_age
+    }
+
+    #[getter]
+    pub fn season_length(&self) -> u32 {
+        self.config.season_length
+    }
+
+    #[getter]
+    pub fn season_amplitude(&self) -> f32 {
+        self.config.season_amplitude
+    }
+
+    #[getter]
+    pub fn prey_gestation(&self) -> u32 {
+        self.config.prey_gestation
+    }
+
+    #[getter]
+    pub fn predator_gestation(&self) -> u32 {
+        self.config.predator_gestation
+    }
+
+    #[getter]
+    pub fn prey_reproduction_min_neighbours(&self) -> u32 {
+        self.config.prey_reproduction_min_neighbours
+    }
+
+    #[getter]
+    pub fn prey_reproduction_max_neighbours(&self) -> u32 {
+        self.config.prey_reproduction_max_neighbours
+    }
+
+    #[getter]
+    pub fn crowding_death_rate(&self) -> f32 {
+        self.config.crowding_death_rate
+    }
+
+    #[getter]
+    pub fn crowding_threshold(&self) -> u32 {
+        self.config.crowding_threshold
+    }
+
+    #[getter]
+    pub fn infection_rate(&self) -> f32 {
+        self.config.infection_rate
+    }
+
+    #[getter]
+    pub fn infection_duration(&self) -> u32 {
+        self.config.infection_duration
+    }
+
+    #[getter]
+    pub fn infection_death_rate(&self) -> f32 {
+        self.config.infection_death_rate
+    }
+
+    #[getter]
+    pub fn initial_infected_fraction(&self) -> f32 {
+        self.config.initial_infected_fraction
+    }
+
+    #[getter]
+    pub fn predator_reproduction_min_neighbours(&self) -> u32 {
+        self.config.predator_reproduction_min_neighbours
+    }
+
+    #[getter]
+    pub fn predator_reproduction_max_neighbours(&self) -> u32 {
+        self.config.predator_reproduction_max_neighbours
+    }
+
+    #[getter]
+    pub fn omnivore_reproduction_min_neighbours(&self) -> u32 {
+        self.config.omnivore_reproduction_min_neighbours
+    }
+
+    #[getter]
+    pub fn omnivore_reproduction_max_neighbours(&self) -> u32 {
+        self.config.omnivore_reproduction_max_neighbours
+    }
+
+    #[getter]
+    pub fn prey_max_energy(&self) -> u32 {
+        self.config.prey_max_energy
+    }
+
+    #[getter]
+    pub fn prey_energy_cost_reproduce(&self) -> u32 {
+        self.config.prey_energy_cost_reproduce
+    }
+
+    #[getter]
+    pub fn max_prey(&self) -> u32 {
+        self.config.max_prey
+    }
+
+    #[getter]
+    pub fn max_predators(&self) -> u32 {
+        self.config.max_predators
+    }
+
+    #[getter]
+    pub fn max_omnivores(&self) -> u32 {
+        self.config.max_omnivores
+    }
+
+    #[getter]
+    pub fn prey_cohesion(&self) -> f32 {
+        self.config.prey_cohesion
+    }
+
+    #[getter]
+    pub fn prey_flee_radius(&self) -> i32 {
+        self.config.prey_flee_radius
+    }
+
+    #[getter]
+    pub fn predator_vision_radius(&self) -> i32 {
+        self.config.predator_vision_radius
+    }
+
+    #[getter]
+    pub fn migration_rate(&self) -> f32 {
+        self.config.migration_rate
+    }
+
+    #[getter]
+    pub fn targeting(&self) -> TargetingMode {
+        self.config.targeting
+    }
+
+    #[getter]
+    pub fn birth_placement(&self) -> PlacementStrategy {
+        self.config.birth_placement
+    }
+
+    #[getter]
+    pub fn predator_mortality(&self) -> PredatorMortality {
+        self.config.predator_mortality
+    }
+
+    #[getter]
+    pub fn allow_diagonal_moves(&self) -> bool {
+        self.config.allow_diagonal_moves
+    }
+
+    #[getter]
+    pub fn prey_index(&self) -> PreyIndex {
+        self.config.prey_index
+    }
+
+    #[getter]
+    pub fn update_mode(&self) -> UpdateMode {
+        self.config.update_mode
+    }
+
+    #[getter]
+    pub fn scent_deposit(&self) -> f32 {
+        self.config.scent_deposit
+    }
+
+    #[getter]
+    pub fn scent_decay(&self) -> f32 {
+        self.config.scent_decay
+    }
+
+    #[getter]
+    pub fn scent_diffusion(&self) -> f32 {
+        self.config.scent_diffusion
+    }
+
+    #[getter]
+    pub fn prey_max_age(&self) -> u32 {
+        self.config.prey_max_age
+    }
+
+    #[getter]
+    pub fn predator_max_age(&self) -> u32 {
+        self.config.predator_max_age
+    }
+
+    #[getter]
+    pub fn grass_regrowth_rate(&self) -> f32 {
+        self.config.grass_regrowth_rate
+    }
+
+    #[getter]
+    pub fn grass_max(&self) -> f32 {
+        self.config.grass_max
+    }
+
+    #[getter]
+    pub fn graze_amount(&self) -> f32 {
+        self.config.graze_amount
+    }
+
+    #[getter]
+    pub fn reproduce_grass_threshold(&self) -> f32 {
+        self.config.reproduce_grass_threshold
+    }
+
+    #[getter]
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.config.neighbourhood
+    }
+
+    #[getter]
+    pub fn topology(&self) -> Topology {
+        self.config.topology
+    }
+
+    #[getter]
+    pub fn neighbourhood_radius(&self) -> i32 {
+        self.config.neighbourhood_radius
+    }
+
+    #[getter]
+    pub fn boundary(&self) -> BoundaryMode {
+        self.config.boundary
+    }
+
+    #[getter]
+    pub fn obstacle_fraction(&self) -> f32 {
+        self.config.obstacle_fraction
+    }
+
+    #[getter]
+    pub fn reproduction_mode(&self) -> ReproductionMode {
+        self.config.reproduction_mode
+    }
+
+    #[getter]
+    pub fn mutation_std(&self) -> f32 {
+        self.config.mutation_std
+    }
+
+    #[getter]
+    pub fn inheritance(&self) -> InheritanceMode {
+        self.config.inheritance
+    }
+
+    #[getter]
+    pub fn parallel(&self) -> bool {
+        self.config.parallel
+    }
+
+    #[getter]
+    pub fn record_history(&self) -> bool {
+        self.config.record_history
+    }
+
+    // Individuals carry their own factors, inherited from their parents: a new rate applies to
+    // the individuals spawned afterwards, by `spawn_*` or `reset`. `predator_death_rate` and
+    // `mutation_rate` are read at every step and apply from the next one. Like `validate`, the
+    // setters reject values outside of `[0, 1]` and leave the rate unchanged.
+
+    #[getter]
+    pub fn prey_reproduction_rate(&self) -> f32 {
+        self.config.prey_reproduction_rate
+    }
+
+    #[setter]
+    pub fn set_prey_reproduction_rate(&mut self, value: f32) -> Result<(), ConfigError> {
+        self.config.prey_reproduction_rate = check_rate("prey_reproduction_rate", value)?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn prey_moving_rate(&self) -> f32 {
+        self.config.prey_moving_rate
+    }
+
+    #[setter]
+    pub fn set_prey_moving_rate(&mut self, value: f32) -> Result<(), ConfigError> {
+        self.config.prey_moving_rate = check_rate("prey_moving_rate", value)?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn predator_reproduction_rate(&self) -> f32 {
+        self.config.predator_reproduction_rate
+    }
+
+    #[setter]
+    pub fn set_predator_reproduction_rate(&mut self, value: f32) -> Result<(), ConfigError> {
+        self.config.predator_reproduction_rate = check_rate("predator_reproduction_rate", value)?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn predator_moving_rate(&self) -> f32 {
+        self.config.predator_moving_rate
+    }
+
+    #[setter]
+    pub fn set_predator_moving_rate(&mut self, value: f32) -> Result<(), ConfigError> {
+        self.config.predator_moving_rate = check_rate("predator_moving_rate", value)?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn predator_hunting_rate(&self) -> f32 {
+        self.config.predator_hunting_rate
+    }
+
+    #[setter]
+    pub fn set_predator_hunting_rate(&mut self, value: f32) -> Result<(), ConfigError> {
+        self.config.predator_hunting_rate = check_rate("predator_hunting_rate", value)?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn predator_death_rate(&self) -> f32 {
+        self.config.predator_death_rate
+    }
+
+    #[setter]
+    pub fn set_predator_death_rate(&mut self, value: f32) -> Result<(), ConfigError> {
+        self.config.predator_death_rate = check_rate("predator_death_rate", value)?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn omnivore_reproduction_rate(&self) -> f32 {
+        self.config.omnivore_reproduction_rate
+    }
+
+    #[setter]
+    pub fn set_omnivore_reproduction_rate(&mut self, value: f32) -> Result<(), ConfigError> {
+        self.config.omnivore_reproduction_rate = check_rate("omnivore_reproduction_rate", value)?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn omnivore_moving_rate(&self) -> f32 {
+        self.config.omnivore_moving_rate
+    }
+
+    #[setter]
+    pub fn set_omnivore_moving_rate(&mut self, value: f32) -> Result<(), ConfigError> {
+        self.config.omnivore_moving_rate = check_rate("omnivore_moving_rate", value)?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn omnivore_hunting_rate(&self) -> f32 {
+        self.config.omnivore_hunting_rate
+    }
+
+    #[setter]
+    pub fn set_omnivore_hunting_rate(&mut self, value: f32) -> Result<(), ConfigError> {
+        self.config.omnivore_hunting_rate = check_rate("omnivore_hunting_rate", value)?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn mutation_rate(&self) -> f32 {
+        self.config.mutation_rate
+    }
+
+    #[setter]
+    pub fn set_mutation_rate(&mut self, value: f32) -> Result<(), ConfigError> {
+        self.config.mutation_rate = check_rate("mutation_rate", value)?;
+        Ok(())
+    }
+
     pub fn init_simulation(&mut self) {
+        if self.config.obstacle_fraction > 0.0 {
+            for cell in self.grid.iter_mut() {
+                if self.rng.random::<f32>() < self.config.obstacle_fraction {
+                    cell.set_obstacle();
+                }
+            }
+        }
+        // Each individual gets its own cell, so the requested counts are met unless the free
+        // cells run out.
+        let mut free_cells = (0..self.grid.len()).filter(|&idx| !self.grid[idx].is_obstacle).collect::<Vec<_>>();
+        free_cells.shuffle(&mut self.rng);
+        // Infects the first prey placed: a random pick with `InitialLayout::Random`, those
+        // the layout prefers most otherwise.
+        let nb_infected = (self.config.initial_infected_fraction * self.config.nb_prey_init as f32).round() as usize;
+        for (i, idx) in self.take_initial_cells(&mut free_cells, CellKind::Prey, self.config.nb_prey_init).into_iter().enumerate() {
+            let mut prey = Prey::new(&self.config);
+            if i < nb_infected {
+                prey.infect(&self.config);
+            }
+            self.grid[idx].place_prey(Box::new(prey));
+        }
+        for idx in self.take_initial_cells(&mut free_cells, CellKind::Predator, self.config.nb_predator_init) {
+            self.grid[idx].place_predator(Box::new(Predator::new(&self.config)));
+        }
+        for idx in self.take_initial_cells(&mut free_cells, CellKind::Omnivore, self.config.nb_omnivore_init) {
+            self.grid[idx].place_omnivore(Box::new(Omnivore::new(&self.config)));
+        }
+        self.collect_positions();
+        self.link_neighbours();
+    }
+
+    /// Links every cell to the cells of its neighbourhood, following the boundary mode.
+    fn link_neighbours(&mut self) {
         let width = self.get_width();
         let height = self.get_height();
-        for _ in 0..self.nb_initial_prey {
-            let mut rng = rand::rng();
-            let x = rng.random_range(0..width);
-            let y = rng.random_range(0..height);
-            get_cell(self, x, y).unwrap().borrow_mut().content = Some(Box::new(Prey::new(self.prey_reproduction_factor, self.prey_moving_factor)));
-            get_cell(self, x, y).unwrap().borrow_mut().is_empty = false;
-            get_cell(self, x, y).unwrap().borrow_mut().is_prey = true;
-        }
-        for _ in 0..self.nb_initial_predators {
-            let mut rng = rand::rng();
-            let x = rng.random_range(0..width);
-            let y = rng.random_range(0..height);
-            get_cell(self, x, y).unwrap().borrow_mut().content = Some(Box::new(Predator::new(x, y, self.predator_reproduction_factor, self.predator_moving_factor, self.predator_hunting_factor, self.predator_death_rate, self.predator_max_hunger, width, height)));
-            get_cell(self, x, y).unwrap().borrow_mut().is_empty = false;
-            get_cell(self, x, y).unwrap().borrow_mut().is_predator = true;
+        for cell in self.grid.iter_mut() {
+            cell.clear_neighbours();
         }
+        // Rows of a hex grid alternate between two sets of offsets.
+        let offsets = [self.neighbour_offsets(0), self.neighbour_offsets(1)];
+        let bounded = self.config.boundary == BoundaryMode::Bounded;
         for i in 0..width {
             for j in 0..height {
-                for (dx, dy) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)] {
-                    let ni = (i + dx + width) % height;
-                    let nj = (j + dy + width) % height;
-                    if ni != i || nj != j {
-                        let cell = get_cell(self, i, j).unwrap();
-                        let neighbour = get_cell(self, ni, nj).unwrap();
-                        cell.borrow_mut().add_neighbour(neighbour);
+                let idx = self.index(i, j);
+                for &(dx, dy) in &offsets[(j % 2) as usize] {
+                    let (ni, nj) = (i + dx, j + dy);
+                    if bounded && (ni < 0 || ni >= width || nj < 0 || nj >= height) {
+                        continue;
+                    }
+                    let pos = (wrap(ni, width), wrap(nj, height));
+                    let neighbour = self.index(pos.0, pos.1);
+                    // Small grids wrap several offsets onto the same cell.
+                    if neighbour != idx && !self.grid[idx].neighbours().contains(&neighbour) {
+                        self.grid[idx].add_neighbour(neighbour, pos);
                     }
                 }
             }
         }
     }
     
-    fn build_kd_tree(&mut self) {
-        self.kd_tree = Some(KdTree::build(self.prey_position.clone()));
+    /// Replaces the grid with `grid`, given as `height` rows of `width` codes as returned by
+    /// `get_grid`. Grass is full again and the step counter restarts at 0. Fails if the
+    /// dimensions don't match the config or a code is unknown.
+    pub fn load_grid(&mut self, grid: Vec<Vec<u8>>) -> PyResult<()> {
+        let (width, height) = (self.config.width, self.config.height);
+        if grid.len() != height as usize || grid.iter().any(|row| row.len() != width as usize) {
+            return Err(PyValueError::new_err(format!("grid must be {height} rows of {width} cells")));
+        }
+        if let Some(code) = grid.iter().flatten().find(|&&code| code > 4) {
+            return Err(PyValueError::new_err(format!("unknown cell code {code}")));
+        }
+        self.width = width;
+        self.height = height;
+        self.grid.clear();
+        self.init_grid();
+        for (y, row) in grid.iter().enumerate() {
+            for (x, &code) in row.iter().enumerate() {
+                let idx = self.index(x as i32, y as i32);
+                match code {
+                    1 => self.grid[idx].place_prey(Box::new(Prey::new(&self.config))),
+                    2 => self.grid[idx].place_predator(Box::new(Predator::new(&self.config))),
+                    3 => self.grid[idx].set_obstacle(),
+                    4 => self.grid[idx].place_omnivore(Box::new(Omnivore::new(&self.config))),
+                    _ => {}
+                }
+            }
+        }
+        self.step = 0;
+        self.history.clear();
+        self.recent_steps.clear();
+        self.collect_positions();
+        self.link_neighbours();
+        Ok(())
     }
 
-    #[inline]
-    fn get_nearest_preys(&self, predator_pos: Vec<(i32, i32)>) -> Vec<(i32, i32)> {
-        if let Some(ref kd_tree) = self.kd_tree {
-            let mut nearest_preys = Vec::new();
-            for (x, y) in predator_pos {
-                let nearest = kd_tree.nearest(&[x,y]);
-                if let Some(nearest) = nearest {
-                    nearest_preys.push((nearest.item[0], nearest.item[1]));
+    /// Restarts the run from a fresh initial population drawn from the stored config.
+    ///
+    /// The grid is reused when its dimensions haven't changed; the RNG is not reseeded, so
+    /// successive resets give different initial populations.
+    pub fn reset(&mut self) {
+        if self.width == self.config.width && self.height == self.config.height {
+            for cell in self.grid.iter_mut() {
+                cell.empty();
+                cell.is_obstacle = false;
+                cell.grass = self.config.grass_max;
+                cell.scent = 0.0;
+            }
+        } else {
+            self.width = self.config.width;
+            self.height = self.config.height;
+            self.grid.clear();
+            self.init_grid();
+        }
+        self.prey_position.clear();
+        self.predator_position.clear();
+        self.prey_index = None;
+        self.predator_kd_tree = None;
+        self.step = 0;
+        self.history.clear();
+        self.recent_steps.clear();
+        self.init_simulation();
+    }
+
+    /// Removes every individual but keeps the grid as is, grass and walls included, so that a
+    /// scenario can be built with the `spawn_*` methods. The step counter restarts at 0.
+    pub fn clear(&mut self) {
+        for cell in self.grid.iter_mut() {
+            cell.empty();
+        }
+        self.prey_position.clear();
+        self.predator_position.clear();
+        self.prey_index = None;
+        self.predator_kd_tree = None;
+        self.step = 0;
+        self.recent_steps.clear();
+    }
+
+    pub fn total_grass(&self) -> f32 {
+        self.grid.iter().map(|cell| cell.grass).sum()
+    }
+
+    fn regrow_grass(&mut self) {
+        let rate = self.config.grass_regrowth_rate * self.current_season_factor();
+        for cell in self.grid.iter_mut() {
+            cell.regrow_grass(rate, self.config.grass_max);
+        }
+    }
+
+    /// Prey mark their cell, then the scent of every cell mixes with its neighbours and fades.
+    fn spread_scent(&mut self) {
+        if self.config.targeting != TargetingMode::Scent {
+            return;
+        }
+        for cell in self.grid.iter_mut().filter(|cell| cell.is_prey()) {
+            cell.scent += self.config.scent_deposit;
+        }
+        let scent = self.grid.iter().map(|cell| cell.scent).collect::<Vec<_>>();
+        let (decay, diffusion) = (self.config.scent_decay, self.config.scent_diffusion);
+        for (cell, &own) in self.grid.iter_mut().zip(&scent) {
+            if cell.is_obstacle || cell.neighbours().is_empty() {
+                continue;
+            }
+            let around = cell.neighbours().iter().map(|&n| scent[n]).sum::<f32>() / cell.neighbours().len() as f32;
+            cell.scent = (1.0 - decay) * ((1.0 - diffusion) * own + diffusion * around);
+        }
+    }
+
+    /// Scale of reproduction and grass regrowth at the current step, 1 without seasons. The
+    /// cycle starts in spring, peaks in summer a quarter of `season_length` later.
+    pub fn current_season_factor(&self) -> f32 {
+        let length = self.config.season_length;
+        if length == 0 {
+            return 1.0;
+        }
+        let phase = (self.step % length as usize) as f32 / length as f32;
+        1.0 + self.config.season_amplitude * (2.0 * std::f32::consts::PI * phase).sin()
+    }
+
+    /// Mean hunger of the living predators, 0 when there are none.
+    pub fn average_predator_hunger(&self) -> f32 {
+        let hungers = self.grid
+            .iter()
+            .filter(|cell| cell.is_predator())
+            .filter_map(|cell| cell.hunger())
+            .collect::<Vec<_>>();
+        if hungers.is_empty() {
+            return 0.0;
+        }
+        hungers.iter().sum::<u32>() as f32 / hungers.len() as f32
+    }
+
+    /// Populations, grass and diversity of the current grid in a single call.
+    pub fn summary(&self) -> SimSummary {
+        let (nb_prey, nb_predators) = self.count_individuals();
+        let nb_omnivores = self.get_nb_omnivores();
+        SimSummary {
+            nb_prey,
+            nb_predators,
+            nb_omnivores,
+            total_grass: self.total_grass(),
+            mean_predator_hunger: self.average_predator_hunger(),
+            shannon_diversity: SimSummary::shannon_index(&[nb_prey, nb_predators, nb_omnivores]),
+        }
+    }
+
+    /// Per-capita birth and death rates of prey and predators over the last `window` steps, up
+    /// to `RATE_WINDOW_MAX`, as the events of those steps over the populations they started
+    /// with. Deaths are whatever the births don't account for in the change of population.
+    /// Python gets a dict.
+    pub fn measured_rates(&self, window: usize) -> Rates {
+        let recent = self.recent_steps.iter().rev().take(window);
+        let steps = recent.len();
+        let (mut prey, mut predators) = ([0usize; 3], [0usize; 3]);
+        for (stats, start, end) in recent {
+            prey = [prey[0] + stats.prey_born as usize, prey[1] + start.0, prey[2] + end.0];
+            predators = [predators[0] + stats.predators_born as usize, predators[1] + start.1, predators[2] + end.1];
+        }
+        // Over every step, those alive at the end are those at the start plus births minus deaths.
+        let per_capita = |[born, start, end]: [usize; 3]| {
+            if start == 0 {
+                return (0.0, 0.0);
+            }
+            let died = start + born - end;
+            (born as f32 / start as f32, died as f32 / start as f32)
+        };
+        let (prey_birth_rate, prey_death_rate) = per_capita(prey);
+        let (predator_birth_rate, predator_death_rate) = per_capita(predators);
+        Rates { prey_birth_rate, prey_death_rate, predator_birth_rate, predator_death_rate, steps }
+    }
+
+    /// Mean of a heritable factor over the individuals carrying it, 0 if there are none.
+    pub fn mean_trait(&self, which: TraitKind) -> f32 {
+        let values = self.grid
+            .iter()
+            .filter_map(|cell| cell.trait_value(which))
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            return 0.0;
+        }
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+
+    /// Number of living individuals of each age, indexed by age.
+    pub fn age_histogram(&self) -> Vec<u32> {
+        let mut histogram = Vec::new();
+        for age in self.grid.iter().filter_map(|cell| cell.age()) {
+            let age = age as usize;
+            if histogram.len() <= age {
+                histogram.resize(age + 1, 0);
+            }
+            histogram[age] += 1;
+        }
+        histogram
+    }
+
+    /// Places a new prey at `(x, y)`. Returns `false` if the cell is out of bounds or taken.
+    pub fn spawn_prey(&mut self, x: i32, y: i32) -> bool {
+        let prey = Box::new(Prey::new(&self.config));
+        match get_cell(self, x, y) {
+            Some(cell) if cell.is_empty() => cell.place_prey(prey),
+            _ => return false,
+        }
+        self.prey_position.push((x, y));
+        true
+    }
+
+    /// Places a new omnivore at `(x, y)`. Returns `false` if the cell is out of bounds or taken.
+    pub fn spawn_omnivore(&mut self, x: i32, y: i32) -> bool {
+        let omnivore = Box::new(Omnivore::new(&self.config));
+        match get_cell(self, x, y) {
+            Some(cell) if cell.is_empty() => cell.place_omnivore(omnivore),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Places a new predator at `(x, y)`. Returns `false` if the cell is out of bounds or taken.
+    pub fn spawn_predator(&mut self, x: i32, y: i32) -> bool {
+        let predator = Box::new(Predator::new(&self.config));
+        match get_cell(self, x, y) {
+            Some(cell) if cell.is_empty() => cell.place_predator(predator),
+            _ => return false,
+        }
+        self.predator_position.push((x, y));
+        true
+    }
+
+    /// Turns `(x, y)` into a wall, removing its occupant if any. Returns `false` out of bounds.
+    pub fn set_obstacle(&mut self, x: i32, y: i32) -> bool {
+        match get_cell(self, x, y) {
+            Some(cell) => {
+                cell.set_obstacle();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// What the cell `(x, y)` holds, or `None` out of bounds. Python gets the kind as a string:
+    /// `"empty"`, `"prey"`, `"predator"`, `"omnivore"` or `"obstacle"`.
+    pub fn cell_kind(&self, x: i32, y: i32) -> Option<CellKind> {
+        get_cell_ref(self, x, y).map(Cell::kind)
+    }
+
+    /// Number of cells of `kind` within `radius` of `(x, y)`, in the neighbourhood shape and
+    /// boundary mode of the config, the cell itself excluded. 0 when `(x, y)` is out of bounds.
+    /// Python passes the kind as a string, see `cell_kind`.
+    pub fn count_species_within(&self, x: i32, y: i32, radius: i32, kind: CellKind) -> usize {
+        if x < 0 || x >= self.width || y < 0 || y >= self.height {
+            return 0;
+        }
+        self.cells_within(x, y, radius).into_iter().filter(|&idx| self.grid[idx].kind() == kind).count()
+    }
+
+    /// `height` rows of `width` values: for each cell, the fraction of the cells within `radius`
+    /// that hold `kind`, as counted by `count_species_within`. Edge cells of a bounded grid are
+    /// divided by their own, smaller, number of neighbours.
+    pub fn density_map(&self, kind: CellKind, radius: i32) -> Vec<Vec<f32>> {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| {
+                        let cells = self.cells_within(x, y, radius);
+                        let count = cells.iter().filter(|&&idx| self.grid[idx].kind() == kind).count();
+                        if cells.is_empty() { 0.0 } else { count as f32 / cells.len() as f32 }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Same as `density_map` but as a `(height, width)` numpy array of `float32`.
+    pub fn density_map_numpy<'py>(&self, py: Python<'py>, kind: CellKind, radius: i32) -> Bound<'py, PyArray2<f32>> {
+        PyArray2::from_vec2(py, &self.density_map(kind, radius)).expect("density rows all have the same width")
+    }
+
+    /// Internal state of the individual at `(x, y)`, `None` if the cell holds none or is out of
+    /// bounds. Python gets a dict.
+    pub fn cell_state(&self, x: i32, y: i32) -> Option<IndividualState> {
+        get_cell_ref(self, x, y).and_then(Cell::state)
+    }
+
+    /// Coordinates of the cells linked as neighbours of `(x, y)`, or `None` out of bounds.
+    pub fn get_neighbours(&self, x: i32, y: i32) -> Option<Vec<(i32, i32)>> {
+        get_cell_ref(self, x, y).map(Cell::neighbour_positions)
+    }
+
+    /// The grid as `height` rows of `width` values: 0 = empty, 1 = prey, 2 = predator,
+    /// 3 = obstacle, 4 = omnivore.
+    pub fn get_grid(&self) -> Vec<Vec<u8>> {
+        (0..self.height)
+            .map(|y| (0..self.width).map(|x| self.grid[self.index(x, y)].kind_code()).collect())
+            .collect()
+    }
+
+    /// Grass of each cell, as `height` rows of `width` values.
+    pub fn get_grass(&self) -> Vec<Vec<f32>> {
+        (0..self.height)
+            .map(|y| (0..self.width).map(|x| self.grid[self.index(x, y)].grass).collect())
+            .collect()
+    }
+
+    /// Same as `get_grid` but as a `(height, width)` numpy array of `uint8`.
+    pub fn get_grid_numpy<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<u8>> {
+        PyArray2::from_vec2(py, &self.get_grid()).expect("grid rows all have the same width")
+    }
+
+    /// Renders the grid as text, one line per row from top to bottom:
+    /// `.` for empty, `o` for prey, `X` for predators, `#` for obstacles, `@` for omnivores.
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::with_capacity(((self.width + 1) * self.height) as usize);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                out.push(match self.grid[self.index(x, y)].kind_code() {
+                    1 => 'o',
+                    2 => 'X',
+                    3 => '#',
+                    4 => '@',
+                    _ => '.',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Stable hash of the step counter, the position lists and every cell, individuals included
+    /// down to their hunger and factors. Two simulations in the same state hash the same, in any
+    /// process; the random generator is left out.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        let state = (self.step, &self.prey_position, &self.predator_position, &self.grid);
+        serde_json::to_writer(&mut hasher, &state).expect("the state serializes like in __getstate__");
+        hasher.0
+    }
+
+    /// Full state of the simulation, RNG included, so that an unpickled copy evolves exactly
+    /// like the original.
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let state = serde_json::to_vec(self).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyBytes::new(py, &state))
+    }
+
+    fn __setstate__(&mut self, state: &[u8]) -> PyResult<()> {
+        *self = serde_json::from_slice(state).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(())
+    }
+
+    fn __str__(&self) -> String {
+        if self.width < 100 && self.height < 100 {
+            self.to_ascii()
+        } else {
+            let (nb_prey, nb_predators) = self.count_individuals();
+            format!(
+                "Simulation({}x{}, {} prey, {} predators)",
+                self.width, self.height, nb_prey, nb_predators
+            )
+        }
+    }
+
+    /// Saves the grid as a PNG where each cell is a `cell_size` square block:
+    /// white for empty, green for prey, red for predators, black for obstacles, blue for
+    /// omnivores.
+    pub fn render_png(&self, path: &str, cell_size: u32) -> PyResult<()> {
+        if cell_size == 0 {
+            return Err(PyValueError::new_err("cell_size must be at least 1"));
+        }
+        let image = RgbImage::from_fn(
+            self.width as u32 * cell_size,
+            self.height as u32 * cell_size,
+            |px, py| {
+                let cell = &self.grid[self.index((px / cell_size) as i32, (py / cell_size) as i32)];
+                match cell.kind_code() {
+                    1 => Rgb([0, 200, 0]),
+                    2 => Rgb([220, 0, 0]),
+                    3 => Rgb([0, 0, 0]),
+                    4 => Rgb([0, 0, 220]),
+                    _ => Rgb([255, 255, 255]),
                 }
+            },
+        );
+        image.save(path).map_err(|e| PyIOError::new_err(e.to_string()))
+    }
+
+    /// Prepares the indexes the phases of a sweep query; positions don't change until the sweep
+    /// starts, so this is done once per sweep and shared by all phases. Most prey stay put from
+    /// one sweep to the next, so the prey index only takes in the positions that changed. The
+    /// predator tree is only needed when prey flee.
+    fn build_kd_tree(&mut self) {
+        let kind = self.config.prey_index;
+        let space = Space { width: self.width, height: self.height, toroidal: self.config.boundary == BoundaryMode::Toroidal };
+        match self.prey_index {
+            Some((built, ref mut index)) if built == kind => index.update(&self.indexed_prey, &self.prey_position),
+            _ => {
+                let index: Box<dyn SpatialIndex + Send + Sync> = match kind {
+                    PreyIndex::KdTree => Box::new(KdTreeIndex::new(space, &self.prey_position)),
+                    PreyIndex::Grid => Box::new(GridIndex::new(space, GRID_BUCKET_SIZE, &self.prey_position)),
+                };
+                self.prey_index = Some((kind, index));
             }
-            nearest_preys
-        }else { 
-            println!("KD Tree not built yet");
-            Vec::new()
         }
+        self.indexed_prey.clone_from(&self.prey_position);
+        self.predator_kd_tree = (self.config.prey_flee_radius > 0).then(|| build_tree(&self.predator_position));
+    }
+
+    /// Nearest prey each predator of the current grid would chase, predators in row-major order.
+    pub fn predator_targets(&mut self) -> Vec<PredatorTarget> {
+        self.collect_positions();
+        self.build_kd_tree();
+        let nearest_prey = self.get_nearest_preys(&self.predator_position);
+        self.predator_position.iter().copied().zip(nearest_prey).collect()
+    }
+
+    /// Coordinates of the prey, as of the end of the last step or the last spawn.
+    pub fn prey_positions(&self) -> Vec<Pos> {
+        self.prey_position.clone()
+    }
+
+    /// Coordinates of the predators, as of the end of the last step or the last spawn.
+    pub fn predator_positions(&self) -> Vec<Pos> {
+        self.predator_position.clone()
     }
 
     pub fn get_nb_prey(&self) -> usize {
@@ -139,68 +1597,274 @@ impl Simulation {
     pub fn get_nb_predators(&self) -> usize {
         self.predator_position.len()
     }
-    
-    fn update_parallel(&mut self, i: i32, j: i32) -> (Vec<[i32; 2]>, Vec<(i32, i32)>) {
-        let mut prey_cell = Vec::new();
-        let mut prey_coords = Vec::new();
-        let mut predator_cell = Vec::new();
-        let mut predator_coords = Vec::new();
-        self.prey_position.clear();
+    /// Number of prey, predators and omnivores.
+    pub fn total_individuals(&self) -> usize {
+        self.prey_position.len() + self.predator_position.len() + self.get_nb_omnivores()
+    }
 
-        for x in (0..self.get_width()).step_by(3) {
-            for y in (0..self.get_height()).step_by(3) {
-                if let Some(cell) = get_cell(self, i + x, j + y) {
-                    let cell_ref = Rc::clone(&cell);
-                    if cell_ref.borrow_mut().is_prey() {
-                        let x = cell_ref.borrow().x;
-                        let y = cell_ref.borrow().y;
-                        prey_coords.push([x, y]);
-                        prey_cell.push(cell_ref);
-                    } else if cell_ref.borrow_mut().is_predator() {
-                        let x = cell_ref.borrow().x;
-                        let y = cell_ref.borrow().y;
-                        predator_coords.push((x, y));
-                        predator_cell.push(cell_ref);
-                    }
-                }else{
-                    println!("Cell not found at ({}, {})", i + x, j + y);
-                }
+    /// Predators per prey, `None` without prey.
+    pub fn predator_prey_ratio(&self) -> Option<f32> {
+        let nb_prey = self.prey_position.len();
+        (nb_prey > 0).then(|| self.predator_position.len() as f32 / nb_prey as f32)
+    }
+
+    /// Fraction of the cells holding a prey or a predator, from the position lists of the last
+    /// step or spawn. Omnivores have no position list and are left out.
+    pub fn occupancy(&self) -> f32 {
+        let (prey, predators) = self.occupancy_by_species();
+        prey + predators
+    }
+
+    /// Fractions of the cells holding a prey and holding a predator, see `occupancy`.
+    pub fn occupancy_by_species(&self) -> (f32, f32) {
+        let nb_cells = (self.width * self.height) as f32;
+        (self.prey_position.len() as f32 / nb_cells, self.predator_position.len() as f32 / nb_cells)
+    }
+
+    pub fn get_nb_omnivores(&self) -> usize {
+        self.grid.iter().filter(|cell| cell.is_omnivore()).count()
+    }
+
+    /// Number of prey carrying the disease, see `infection_rate`.
+    pub fn get_nb_infected(&self) -> usize {
+        self.grid.iter().filter(|cell| cell.is_infected()).count()
+    }
+    
+    /// Runs one step and returns the prey and predator positions at its end.
+    pub fn simulate(&mut self) -> (Vec<Pos>, Vec<Pos>) {
+        self.stats = StepStats::default();
+        self.regrow_grass();
+        self.spread_scent();
+        self.collect_positions();
+        if self.migrate_predators() {
+            self.collect_positions();
+        }
+        self.build_kd_tree();
+        for cell in self.grid.iter_mut() {
+            cell.arrived = false;
+        }
+        // The grid is the buffer the step writes to, this copy the one it reads from.
+        let previous = (self.config.update_mode == UpdateMode::Synchronous).then(|| self.grid.clone());
+        let (nb_prey, nb_predators) = self.count_individuals();
+        let census = Census {
+            prey: nb_prey.into(),
+            predators: nb_predators.into(),
+            omnivores: self.get_nb_omnivores().into(),
+        };
+        let step = self.phase_step();
+        for i in 0..step {
+            for j in 0..step {
+                self.update_parallel(i, j, &census, previous.as_deref());
             }
         }
+        // Individuals died, were born and moved during the phases.
+        self.collect_positions();
+        self.step += 1;
+        let end = self.count_individuals();
+        if self.recent_steps.len() == RATE_WINDOW_MAX {
+            self.recent_steps.pop_front();
+        }
+        self.recent_steps.push_back((self.stats, (nb_prey, nb_predators), end));
+        if self.config.record_history {
+            self.history.push((self.step, end.0, end.1));
+        }
+        // self.update_parallel(0, 0);
+        (self.prey_position.clone(), self.predator_position.clone())
+    }
+
+    /// Starts logging births, deaths, kills and moves, keeping the last `cap` events; 0 stops the
+    /// log and drops its events.
+    pub fn enable_event_log(&mut self, cap: usize) {
+        self.event_log_cap = cap;
+        while self.events.len() > cap {
+            self.events.pop_front();
+        }
+    }
 
-        let nearest_prey = self.get_nearest_preys(predator_coords.clone());
+    /// Removes and returns the logged events, oldest first. Python gets a list of dicts.
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        self.events.drain(..).collect()
+    }
 
-        for cell in prey_cell {
-            cell.borrow_mut().update(None);
+    /// `(step, nb_prey, nb_predators)` recorded after each step since the start or the last reset.
+    pub fn population_history(&self) -> Vec<(usize, usize, usize)> {
+        self.history.clone()
+    }
+
+    /// Writes the population history to `path` as CSV with a `step,prey,predators` header.
+    pub fn export_history_csv(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "step,prey,predators")?;
+        for &(step, nb_prey, nb_predators) in &self.history {
+            writeln!(out, "{step},{nb_prey},{nb_predators}")?;
         }
-        for (i, cell) in predator_cell.into_iter().enumerate() {
-            let nearest_prey = if i < nearest_prey.len() {
-                Some(nearest_prey[i])
-            } else {
-                None
-            };
-            cell.borrow_mut().update(nearest_prey);
+        out.flush()
+    }
+
+    /// Runs `steps` steps and writes the population after each of them to `path`, as CSV with
+    /// the `export_history_csv` header, starting with a row for the current state. The history
+    /// is still recorded if `config.record_history` is set.
+    pub fn run_to_csv(&mut self, steps: usize, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "step,prey,predators")?;
+        let (nb_prey, nb_predators) = self.count_individuals();
+        writeln!(out, "{},{nb_prey},{nb_predators}", self.step)?;
+        for _ in 0..steps {
+            self.update();
+            let (nb_prey, nb_predators) = self.count_individuals();
+            writeln!(out, "{},{nb_prey},{nb_predators}", self.step)?;
         }
+        out.flush()
+    }
 
-        (prey_coords.clone(), predator_coords.clone())
+    /// Iterator running one step per item, up to `n` steps, and yielding the
+    /// `(nb_prey, nb_predators)` after each of them. It stops early once both species are
+    /// extinct.
+    pub fn iter_steps(slf: Bound<'_, Self>, n: usize) -> SimulationStepper {
+        SimulationStepper::new(slf.unbind(), n)
     }
 
-    pub fn simulate(&mut self) -> (Vec<[i32; 2]>, Vec<(i32, i32)>) {
-        let mut prey_pos = Vec::new();
-        let mut predator_pos = Vec::new();
-        self.build_kd_tree();
-        for i in [0, 1, 2] {
-            for j in [0, 1, 2] {
-                let (prey_cell, predator_cell) = self.update_parallel(i, j);
-                prey_pos.extend(prey_cell);
-                predator_pos.extend(predator_cell);
+    /// Runs `n` steps, or fewer if the stop condition is met, without going back to Python in
+    /// between. Returns the number of steps run and the sum of their events.
+    pub fn step_n(&mut self, n: usize) -> (usize, StepStats) {
+        let (outcome, stats) = self.run_steps(n, self.stop_condition);
+        (outcome.step, stats)
+    }
+
+    /// Runs up to `n` steps, calling `callback(step, nb_prey, nb_predators)` after each one.
+    ///
+    /// The run stops early when the callback returns `False`, and an exception raised by the
+    /// callback is propagated. Returns the number of steps run.
+    pub fn run_with_callback(&mut self, py: Python<'_>, n: usize, callback: PyObject) -> PyResult<usize> {
+        for step in 1..=n {
+            self.update();
+            let (nb_prey, nb_predators) = self.count_individuals();
+            let result = callback.call1(py, (self.step, nb_prey, nb_predators))?;
+            if matches!(result.extract::<bool>(py), Ok(false)) {
+                return Ok(step);
             }
         }
-        
+        Ok(n)
+    }
 
-        self.prey_position = prey_pos;
-        self.predator_position = predator_pos;
-        // self.update_parallel(0, 0);
-        (self.prey_position.clone(), self.predator_position.clone())
+    /// Same as `run_for`, with a budget in milliseconds.
+    #[pyo3(name = "run_for")]
+    fn py_run_for(&mut self, budget_ms: u64) -> usize {
+        self.run_for(Duration::from_millis(budget_ms))
+    }
+
+    /// Runs one step and returns the events that happened during it.
+    pub fn update(&mut self) -> StepStats {
+        self.simulate();
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_visit_every_cell_once() {
+        for (width, height, step) in [(7, 5, 3), (10, 11, 3), (4, 4, 5), (1, 13, 3), (9, 9, 3)] {
+            let mut visits = vec![0; (width * height) as usize];
+            for i in 0..step {
+                for j in 0..step {
+                    for y in phase_lines(height, j, step) {
+                        for x in phase_lines(width, i, step) {
+                            visits[(y * width + x) as usize] += 1;
+                        }
+                    }
+                }
+            }
+            assert!(visits.iter().all(|&v| v == 1), "{width}x{height} with step {step}");
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn disjoint_bands_update_on_separate_threads() {
+        let config = SimulationConfig {
+            width: 6,
+            height: 6,
+            boundary: BoundaryMode::Bounded,
+            nb_prey_init: 36,
+            nb_predator_init: 0,
+            prey_max_age: 1,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config.clone(), 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let census = Census::default();
+        // Rows 1 and 4 only reach into rows 0..3 and 3..6, so each band gets its own thread.
+        let (top, bottom) = sim.grid.split_at_mut(18);
+        std::thread::scope(|scope| {
+            for (band, offset, y) in [(top, 0, 1), (bottom, 18, 4)] {
+                let (config, census) = (&config, &census);
+                scope.spawn(move || {
+                    let mut grid = GridSlice::new(band, offset, None);
+                    let mut rng = StdRng::seed_from_u64(0);
+                    let mut ctx = Context { config, rng: &mut rng, stats: StepStats::default(), season_factor: 1.0, census, events: None };
+                    for x in 0..6 {
+                        Cell::update(&mut grid, y * 6 + x, None, &mut ctx);
+                    }
+                });
+            }
+        });
+        let rows = sim.get_grid().iter().map(|row| row.iter().map(|&v| v as usize).sum::<usize>()).collect::<Vec<_>>();
+        assert_eq!(rows, [6, 0, 6, 6, 0, 6]);
+    }
+
+    #[test]
+    fn empty_cells_are_picked_regardless_of_neighbour_order() {
+        let config = SimulationConfig {
+            width: 12,
+            height: 12,
+            nb_prey_init: 20,
+            nb_predator_init: 0,
+            prey_moving_rate: 0.8,
+            // A sexual prey tries its neighbours in order, only the empty cells are sorted.
+            reproduction_mode: ReproductionMode::Asexual,
+            ..Default::default()
+        };
+        let mut a = Simulation::with_seed(config.clone(), 13).unwrap();
+        let mut b = Simulation::with_seed(config, 13).unwrap();
+        for sim in [&mut a, &mut b] {
+            sim.init_grid();
+            sim.init_simulation();
+        }
+        for cell in b.grid.iter_mut() {
+            let reversed = cell.neighbours().iter().copied().zip(cell.neighbour_positions()).rev().collect::<Vec<_>>();
+            cell.clear_neighbours();
+            for (n, pos) in reversed {
+                cell.add_neighbour(n, pos);
+            }
+        }
+        for _ in 0..30 {
+            assert_eq!(a.simulate(), b.simulate());
+        }
+    }
+    #[test]
+    fn nearest_prey_is_found_across_the_wrap() {
+        for prey_index in [PreyIndex::KdTree, PreyIndex::Grid] {
+            let config = SimulationConfig {
+                width: 10,
+                height: 10,
+                nb_prey_init: 0,
+                nb_predator_init: 0,
+                prey_index,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 0).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            assert!(sim.spawn_prey(9, 0));
+            sim.build_kd_tree();
+            assert_eq!(sim.get_nearest_preys(&[(0, 0)]), [Some((9, 0))], "{prey_index:?}");
+            // Closer by raw coordinates, but 3 cells away against 1 across the edge.
+            assert!(sim.spawn_prey(3, 0));
+            sim.build_kd_tree();
+            assert_eq!(sim.get_nearest_preys(&[(0, 0)]), [Some((9, 0))], "{prey_index:?}");
+        }
+    }
+}
@@ -0,0 +1,463 @@
+use std::cmp::Ordering;
+
+use kd_tree::KdTree;
+
+use crate::geom::{toroidal_delta, wrap};
+use crate::simulation::Pos;
+
+/// Side of the buckets of the `PreyIndex::Grid` index.
+pub const GRID_BUCKET_SIZE: i32 = 4;
+
+/// Grid the positions of an index lie on.
+#[derive(Clone, Copy, Debug)]
+pub struct Space {
+    pub width: i32,
+    pub height: i32,
+    /// Opposite edges are joined and distances are measured the short way round, see
+    /// `BoundaryMode::Toroidal`.
+    pub toroidal: bool,
+}
+
+impl Space {
+    fn delta(&self, from: i32, to: i32, len: i32) -> i64 {
+        (if self.toroidal { toroidal_delta(from, to, len) } else { to - from }).abs() as i64
+    }
+
+    fn distance_sq(&self, a: Pos, b: Pos) -> i64 {
+        let dx = self.delta(a.0, b.0, self.width);
+        let dy = self.delta(a.1, b.1, self.height);
+        dx * dx + dy * dy
+    }
+
+    fn wrap(&self, (x, y): Pos) -> Pos {
+        if self.toroidal {
+            (wrap(x, self.width), wrap(y, self.height))
+        } else {
+            (x, y)
+        }
+    }
+}
+
+/// Row-major order of the grid, the order in which `Simulation` lists positions.
+fn row_major(a: &Pos, b: &Pos) -> Ordering {
+    (a.1, a.0).cmp(&(b.1, b.0))
+}
+
+/// Orders candidates of a nearest query: closest first, ties going to the first position in
+/// row-major order so that every index, however it got its positions, picks the same one.
+fn nearest_key(space: Space, pos: Pos, query: Pos) -> (i64, i32, i32) {
+    (space.distance_sq(pos, query), pos.1, pos.0)
+}
+
+/// Nearest-neighbour index over distinct grid positions, which follows the changes of the set
+/// instead of being rebuilt.
+pub trait SpatialIndex {
+    fn insert(&mut self, pos: Pos);
+
+    /// Removes `pos` and returns whether it was indexed.
+    fn remove(&mut self, pos: Pos) -> bool;
+
+    /// Indexed position closest to `pos` in euclidean distance, see `nearest_key` for ties.
+    /// On a torus the distance is measured the short way round.
+    fn nearest(&self, pos: Pos) -> Option<Pos>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Takes the index from the positions `old` to `new`, both in row-major order, by removing
+    /// and inserting only the positions that differ.
+    fn update(&mut self, old: &[Pos], new: &[Pos]) {
+        let (removed, added) = diff(old, new);
+        for pos in removed {
+            self.remove(pos);
+        }
+        for pos in added {
+            self.insert(pos);
+        }
+    }
+}
+
+/// Positions of `old` missing from `new` and positions of `new` missing from `old`, both lists
+/// being in row-major order.
+fn diff(old: &[Pos], new: &[Pos]) -> (Vec<Pos>, Vec<Pos>) {
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        let order = match (old.get(i), new.get(j)) {
+            (Some(a), Some(b)) => row_major(a, b),
+            (Some(_), None) => Ordering::Less,
+            _ => Ordering::Greater,
+        };
+        match order {
+            Ordering::Less => {
+                removed.push(old[i]);
+                i += 1;
+            }
+            Ordering::Greater => {
+                added.push(new[j]);
+                j += 1;
+            }
+            Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    (removed, added)
+}
+
+fn build_tree(positions: &[Pos]) -> KdTree<[i32; 2]> {
+    KdTree::build(positions.iter().map(|&(x, y)| [x, y]).collect())
+}
+
+/// First position in row-major order at exactly `distance_sq` from `pos` for which `indexed`
+/// holds, found by walking the lattice points on that circle.
+fn first_on_circle(space: Space, pos: Pos, distance_sq: i64, indexed: impl Fn(Pos) -> bool) -> Option<Pos> {
+    let radius = distance_sq.isqrt();
+    (-radius..=radius)
+        .flat_map(|dy| {
+            let dx_sq = distance_sq - dy * dy;
+            let dx = dx_sq.isqrt();
+            let (dx, dy) = (dx as i32, dy as i32);
+            let on_circle = (dx as i64) * (dx as i64) == dx_sq;
+            [(pos.0 - dx, pos.1 + dy), (pos.0 + dx, pos.1 + dy)].into_iter().filter(move |_| on_circle)
+        })
+        .map(|p| space.wrap(p))
+        .filter(|&p| indexed(p))
+        .min_by(row_major)
+}
+
+/// Which cells of a grid hold an indexed position.
+struct Occupancy {
+    space: Space,
+    cells: Vec<bool>,
+    len: usize,
+}
+
+impl Occupancy {
+    fn new(space: Space) -> Self {
+        Occupancy { space, cells: vec![false; (space.width * space.height) as usize], len: 0 }
+    }
+
+    fn contains(&self, (x, y): Pos) -> bool {
+        let Space { width, height, .. } = self.space;
+        x >= 0 && x < width && y >= 0 && y < height && self.cells[(y * width + x) as usize]
+    }
+
+    /// Marks `pos` as `value` and returns whether it changed.
+    fn set(&mut self, (x, y): Pos, value: bool) -> bool {
+        let cell = &mut self.cells[(y * self.space.width + x) as usize];
+        let changed = *cell != value;
+        *cell = value;
+        if changed {
+            if value {
+                self.len += 1;
+            } else {
+                self.len -= 1;
+            }
+        }
+        changed
+    }
+}
+
+/// KdTree that keeps the changes since its last build aside, in a small tree of its own and a
+/// set of removed positions, and is only rebuilt once they amount to a quarter of it.
+///
+/// The trees know nothing of the torus: a query close to an edge is also run from its image one
+/// lap away, across that edge.
+pub struct KdTreeIndex {
+    space: Space,
+    tree: KdTree<[i32; 2]>,
+    /// Inserted since the last build.
+    added: Vec<Pos>,
+    added_tree: KdTree<[i32; 2]>,
+    /// Removed since the last build but still in `tree`.
+    removed: Occupancy,
+    /// Everything indexed, to break ties between positions as close to a query.
+    positions: Occupancy,
+}
+
+impl KdTreeIndex {
+    pub fn new(space: Space, positions: &[Pos]) -> Self {
+        let mut occupancy = Occupancy::new(space);
+        for &pos in positions {
+            occupancy.set(pos, true);
+        }
+        KdTreeIndex {
+            space,
+            tree: build_tree(positions),
+            added: Vec::new(),
+            added_tree: build_tree(&[]),
+            removed: Occupancy::new(space),
+            positions: occupancy,
+        }
+    }
+
+    fn insert_pending(&mut self, pos: Pos) {
+        if self.positions.set(pos, true) && !self.removed.set(pos, false) {
+            self.added.push(pos);
+        }
+    }
+
+    fn remove_pending(&mut self, pos: Pos) -> bool {
+        if !self.positions.set(pos, false) {
+            return false;
+        }
+        match self.added.iter().position(|&added| added == pos) {
+            Some(i) => {
+                self.added.swap_remove(i);
+            }
+            None => {
+                self.removed.set(pos, true);
+            }
+        }
+        true
+    }
+
+    /// Takes in the pending changes, rebuilding everything once they are too many.
+    fn refresh(&mut self) {
+        if self.added.len() + self.removed.len <= self.tree.len() / 4 + 16 {
+            self.added_tree = build_tree(&self.added);
+            return;
+        }
+        let Space { width, height, .. } = self.space;
+        let positions = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&pos| self.positions.contains(pos))
+            .collect::<Vec<_>>();
+        *self = KdTreeIndex::new(self.space, &positions);
+    }
+
+    /// Distance to the closest position of `tree` that wasn't removed.
+    fn nearest_kept_distance(&self, pos: Pos) -> Option<i64> {
+        let query = [pos.0, pos.1];
+        let nearest = self.tree.nearest(&query)?;
+        if !self.removed.contains((nearest.item[0], nearest.item[1])) {
+            return Some(nearest.squared_distance as i64);
+        }
+        let mut k = 2;
+        loop {
+            let nearests = self.tree.nearests(&query, k);
+            let kept = nearests.iter().find(|nearest| !self.removed.contains((nearest.item[0], nearest.item[1])));
+            if let Some(kept) = kept {
+                return Some(kept.squared_distance as i64);
+            }
+            if nearests.len() < k {
+                return None;
+            }
+            k *= 2;
+        }
+    }
+
+    /// Straight-line distance to the closest indexed position, ignoring the torus.
+    fn nearest_distance(&self, pos: Pos) -> Option<i64> {
+        let from_added = self.added_tree.nearest(&[pos.0, pos.1]).map(|nearest| nearest.squared_distance as i64);
+        self.nearest_kept_distance(pos).into_iter().chain(from_added).min()
+    }
+}
+
+impl SpatialIndex for KdTreeIndex {
+    fn insert(&mut self, pos: Pos) {
+        self.insert_pending(pos);
+        self.refresh();
+    }
+
+    fn remove(&mut self, pos: Pos) -> bool {
+        let removed = self.remove_pending(pos);
+        self.refresh();
+        removed
+    }
+
+    fn nearest(&self, pos: Pos) -> Option<Pos> {
+        let mut distance_sq = self.nearest_distance(pos)?;
+        if self.space.toroidal {
+            let Space { width, height, .. } = self.space;
+            // Each image is only worth a query when the edge it lies across is close enough.
+            let (shift_x, gap_x) = if pos.0 < width / 2 { (width, pos.0 + 1) } else { (-width, width - pos.0) };
+            let (shift_y, gap_y) = if pos.1 < height / 2 { (height, pos.1 + 1) } else { (-height, height - pos.1) };
+            let (gap_x, gap_y) = ((gap_x as i64).pow(2), (gap_y as i64).pow(2));
+            for (dx, dy, gap) in [(shift_x, 0, gap_x), (0, shift_y, gap_y), (shift_x, shift_y, gap_x + gap_y)] {
+                if gap < distance_sq {
+                    let image = self.nearest_distance((pos.0 + dx, pos.1 + dy));
+                    distance_sq = distance_sq.min(image.unwrap_or(i64::MAX));
+                }
+            }
+        }
+        first_on_circle(self.space, pos, distance_sq, |p| self.positions.contains(p))
+    }
+
+    fn len(&self) -> usize {
+        self.positions.len
+    }
+
+    fn update(&mut self, old: &[Pos], new: &[Pos]) {
+        let (removed, added) = diff(old, new);
+        for pos in removed {
+            self.remove_pending(pos);
+        }
+        for pos in added {
+            self.insert_pending(pos);
+        }
+        self.refresh();
+    }
+}
+
+/// Positions sorted into square buckets; a query searches rings of buckets around its own until
+/// no farther ring can hold a closer position.
+pub struct GridIndex {
+    space: Space,
+    bucket_size: i32,
+    columns: i32,
+    rows: i32,
+    buckets: Vec<Vec<Pos>>,
+    len: usize,
+}
+
+impl GridIndex {
+    pub fn new(space: Space, bucket_size: i32, positions: &[Pos]) -> Self {
+        let columns = (space.width + bucket_size - 1) / bucket_size;
+        let rows = (space.height + bucket_size - 1) / bucket_size;
+        let mut index = GridIndex {
+            space,
+            bucket_size,
+            columns,
+            rows,
+            buckets: vec![Vec::new(); (columns * rows) as usize],
+            len: 0,
+        };
+        for &pos in positions {
+            index.insert(pos);
+        }
+        index
+    }
+
+    fn bucket_of(&self, pos: Pos) -> (i32, i32) {
+        let clamp = |v: i32, len: i32| (v / self.bucket_size).clamp(0, len - 1);
+        (clamp(pos.0, self.columns), clamp(pos.1, self.rows))
+    }
+
+    fn bucket(&self, bx: i32, by: i32) -> &[Pos] {
+        &self.buckets[(by * self.columns + bx) as usize]
+    }
+}
+
+impl SpatialIndex for GridIndex {
+    fn insert(&mut self, pos: Pos) {
+        let (bx, by) = self.bucket_of(pos);
+        self.buckets[(by * self.columns + bx) as usize].push(pos);
+        self.len += 1;
+    }
+
+    fn remove(&mut self, pos: Pos) -> bool {
+        let (bx, by) = self.bucket_of(pos);
+        let bucket = &mut self.buckets[(by * self.columns + bx) as usize];
+        match bucket.iter().position(|&p| p == pos) {
+            Some(i) => {
+                bucket.swap_remove(i);
+                self.len -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn nearest(&self, pos: Pos) -> Option<Pos> {
+        let (bx, by) = self.bucket_of(pos);
+        // Across the seam of a torus, the last and narrower buckets bring farther rings closer.
+        let slack = if self.space.toroidal {
+            (self.columns * self.bucket_size - self.space.width).max(self.rows * self.bucket_size - self.space.height)
+        } else {
+            0
+        };
+        let mut best: Option<((i64, i32, i32), Pos)> = None;
+        for ring in 0..=self.columns.max(self.rows) {
+            // Positions in this ring are at least this far along one axis.
+            let gap = ((ring - 1) * self.bucket_size + 1 - slack).max(0) as i64;
+            if best.is_some_and(|((d, _, _), _)| d < gap * gap) {
+                break;
+            }
+            for y in by - ring..=by + ring {
+                for x in bx - ring..=bx + ring {
+                    let on_ring = (x - bx).abs() == ring || (y - by).abs() == ring;
+                    let (x, y) = if self.space.toroidal {
+                        (x.rem_euclid(self.columns), y.rem_euclid(self.rows))
+                    } else {
+                        (x, y)
+                    };
+                    if !on_ring || x < 0 || x >= self.columns || y < 0 || y >= self.rows {
+                        continue;
+                    }
+                    for &p in self.bucket(x, y) {
+                        let key = nearest_key(self.space, p, pos);
+                        if best.is_none_or(|(best_key, _)| key < best_key) {
+                            best = Some((key, p));
+                        }
+                    }
+                }
+            }
+        }
+        best.map(|(_, p)| p)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn indexes_agree_with_a_brute_force_search() {
+        for (seed, count, toroidal) in [(3, 300, false), (4, 300, true), (5, 12, false), (6, 12, true), (7, 2000, true)] {
+            let space = Space { width: 61, height: 45, toroidal };
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut cells = (0..space.height).flat_map(|y| (0..space.width).map(move |x| (x, y))).collect::<Vec<_>>();
+            cells.shuffle(&mut rng);
+            let mut positions = cells[..count].to_vec();
+            positions.sort_by(row_major);
+            let mut kd = KdTreeIndex::new(space, &positions);
+            let mut grid = GridIndex::new(space, 8, &positions);
+            for round in 0..20 {
+                // Move a few positions, as prey do during a step.
+                cells.shuffle(&mut rng);
+                let mut next = positions.clone();
+                next.truncate(positions.len() - 5 * (round % 3));
+                next.extend(cells.iter().filter(|c| !positions.contains(c)).take(10));
+                next.sort_by(row_major);
+                kd.update(&positions, &next);
+                grid.update(&positions, &next);
+                positions = next;
+                let fresh = KdTreeIndex::new(space, &positions);
+                assert_eq!((kd.len(), grid.len()), (positions.len(), positions.len()));
+                for _ in 0..50 {
+                    let query = (rng.random_range(0..space.width), rng.random_range(0..space.height));
+                    let expected = positions.iter().copied().min_by_key(|&p| nearest_key(space, p, query));
+                    assert_eq!(kd.nearest(query), expected);
+                    assert_eq!(grid.nearest(query), expected);
+                    assert_eq!(fresh.nearest(query), expected);
+                }
+            }
+            assert!(GridIndex::new(space, 8, &[]).nearest((3, 3)).is_none());
+        }
+    }
+
+    #[test]
+    fn the_nearest_position_on_a_torus_can_be_across_an_edge() {
+        let torus = Space { width: 20, height: 10, toroidal: true };
+        let positions = [(19, 0), (5, 5)];
+        assert_eq!(KdTreeIndex::new(torus, &positions).nearest((1, 9)), Some((19, 0)));
+        assert_eq!(GridIndex::new(torus, 8, &positions).nearest((1, 9)), Some((19, 0)));
+        let bounded = Space { toroidal: false, ..torus };
+        assert_eq!(KdTreeIndex::new(bounded, &positions).nearest((1, 9)), Some((5, 5)));
+        assert_eq!(GridIndex::new(bounded, 8, &positions).nearest((1, 9)), Some((5, 5)));
+    }
+}
@@ -0,0 +1,193 @@
+use std::ops::AddAssign;
+
+use pyo3::types::{PyDict, PyDictMethods};
+use pyo3::{pyclass, Bound, IntoPyObject, PyErr, Python};
+use serde::{Deserialize, Serialize};
+
+use crate::cell::CellKind;
+
+/// Heritable factor of a species, see `Simulation::mean_trait`.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraitKind {
+    PreyMoving,
+    PreyReproduction,
+    PredatorMoving,
+    PredatorReproduction,
+    PredatorHunting,
+    OmnivoreMoving,
+    OmnivoreReproduction,
+    OmnivoreHunting,
+}
+
+/// Events counted during a single step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepStats {
+    pub prey_born: u32,
+    pub predators_born: u32,
+    pub omnivores_born: u32,
+    /// Prey eaten by predators and omnivores.
+    pub prey_eaten: u32,
+    pub predators_starved: u32,
+    pub omnivores_starved: u32,
+    /// Deaths of old age, every species included.
+    pub deaths_by_old_age: u32,
+    /// Prey killed by overcrowding, see `crowding_death_rate`.
+    pub prey_crowded: u32,
+    /// Prey that caught the disease, see `infection_rate`.
+    pub prey_infected: u32,
+    /// Prey killed by the disease.
+    pub prey_died_of_infection: u32,
+    /// Individuals that couldn't move or breed because every neighbouring cell was taken.
+    pub blocked_moves: u32,
+    /// Prey with a predator within `prey_flee_radius` and no empty neighbour farther from it.
+    pub prey_cornered: u32,
+    /// Births cancelled because the species reached its `max_*` population.
+    pub births_suppressed: u32,
+}
+
+impl AddAssign for StepStats {
+    fn add_assign(&mut self, other: Self) {
+        self.prey_born += other.prey_born;
+        self.predators_born += other.predators_born;
+        self.omnivores_born += other.omnivores_born;
+        self.prey_eaten += other.prey_eaten;
+        self.predators_starved += other.predators_starved;
+        self.omnivores_starved += other.omnivores_starved;
+        self.deaths_by_old_age += other.deaths_by_old_age;
+        self.prey_crowded += other.prey_crowded;
+        self.prey_infected += other.prey_infected;
+        self.prey_died_of_infection += other.prey_died_of_infection;
+        self.blocked_moves += other.blocked_moves;
+        self.prey_cornered += other.prey_cornered;
+        self.births_suppressed += other.births_suppressed;
+    }
+}
+
+impl<'py> IntoPyObject<'py> for StepStats {
+    type Target = PyDict;
+    type Output = Bound<'py, PyDict>;
+    type Error = PyErr;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        let dict = PyDict::new(py);
+        dict.set_item("prey_born", self.prey_born)?;
+        dict.set_item("predators_born", self.predators_born)?;
+        dict.set_item("omnivores_born", self.omnivores_born)?;
+        dict.set_item("prey_eaten", self.prey_eaten)?;
+        dict.set_item("predators_starved", self.predators_starved)?;
+        dict.set_item("omnivores_starved", self.omnivores_starved)?;
+        dict.set_item("deaths_by_old_age", self.deaths_by_old_age)?;
+        dict.set_item("prey_crowded", self.prey_crowded)?;
+        dict.set_item("prey_infected", self.prey_infected)?;
+        dict.set_item("prey_died_of_infection", self.prey_died_of_infection)?;
+        dict.set_item("blocked_moves", self.blocked_moves)?;
+        dict.set_item("prey_cornered", self.prey_cornered)?;
+        dict.set_item("births_suppressed", self.births_suppressed)?;
+        Ok(dict)
+    }
+}
+
+/// Snapshot of the populations and of the grass, see `Simulation::summary`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SimSummary {
+    pub nb_prey: usize,
+    pub nb_predators: usize,
+    pub nb_omnivores: usize,
+    pub total_grass: f32,
+    pub mean_predator_hunger: f32,
+    /// Shannon index `-sum(p * ln(p))` over the proportions `p` of the species present, 0 when
+    /// the grid is empty.
+    pub shannon_diversity: f32,
+}
+
+impl SimSummary {
+    pub(crate) fn shannon_index(counts: &[usize]) -> f32 {
+        let total = counts.iter().sum::<usize>() as f32;
+        counts
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f32 / total;
+                -p * p.ln()
+            })
+            .sum()
+    }
+}
+
+impl<'py> IntoPyObject<'py> for SimSummary {
+    type Target = PyDict;
+    type Output = Bound<'py, PyDict>;
+    type Error = PyErr;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        let dict = PyDict::new(py);
+        dict.set_item("nb_prey", self.nb_prey)?;
+        dict.set_item("nb_predators", self.nb_predators)?;
+        dict.set_item("nb_omnivores", self.nb_omnivores)?;
+        dict.set_item("total_grass", self.total_grass)?;
+        dict.set_item("mean_predator_hunger", self.mean_predator_hunger)?;
+        dict.set_item("shannon_diversity", self.shannon_diversity)?;
+        Ok(dict)
+    }
+}
+
+/// Per-capita rates measured over the last steps, see `Simulation::measured_rates`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rates {
+    /// Births per prey alive at the start of a step.
+    pub prey_birth_rate: f32,
+    /// Deaths per prey alive at the start of a step, whatever the cause.
+    pub prey_death_rate: f32,
+    pub predator_birth_rate: f32,
+    pub predator_death_rate: f32,
+    /// Number of steps measured, fewer than asked when fewer were run.
+    pub steps: usize,
+}
+
+impl<'py> IntoPyObject<'py> for Rates {
+    type Target = PyDict;
+    type Output = Bound<'py, PyDict>;
+    type Error = PyErr;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        let dict = PyDict::new(py);
+        dict.set_item("prey_birth_rate", self.prey_birth_rate)?;
+        dict.set_item("prey_death_rate", self.prey_death_rate)?;
+        dict.set_item("predator_birth_rate", self.predator_birth_rate)?;
+        dict.set_item("predator_death_rate", self.predator_death_rate)?;
+        dict.set_item("steps", self.steps)?;
+        Ok(dict)
+    }
+}
+
+/// Internal state of one individual, see `Simulation::cell_state`. Fields the species doesn't
+/// have are `None`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IndividualState {
+    pub species: CellKind,
+    pub age: u32,
+    pub hunger: Option<u32>,
+    pub energy: Option<u32>,
+    pub reproduction_factor: f32,
+    pub moving_factor: f32,
+    pub hunting_factor: Option<f32>,
+}
+
+impl<'py> IntoPyObject<'py> for IndividualState {
+    type Target = PyDict;
+    type Output = Bound<'py, PyDict>;
+    type Error = PyErr;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        let dict = PyDict::new(py);
+        dict.set_item("species", self.species)?;
+        dict.set_item("age", self.age)?;
+        dict.set_item("hunger", self.hunger)?;
+        dict.set_item("energy", self.energy)?;
+        dict.set_item("reproduction_factor", self.reproduction_factor)?;
+        dict.set_item("moving_factor", self.moving_factor)?;
+        dict.set_item("hunting_factor", self.hunting_factor)?;
+        Ok(dict)
+    }
+}
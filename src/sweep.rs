@@ -0,0 +1,130 @@
+use rayon::prelude::*;
+
+use crate::config::{ConfigError, SimulationConfig};
+use crate::simulation::Simulation;
+use crate::stats::{SimSummary, StepStats};
+
+/// Field of `SimulationConfig` a sweep can vary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamKey {
+    PreyReproductionRate,
+    PreyMovingRate,
+    PredatorReproductionRate,
+    PredatorMovingRate,
+    PredatorHuntingRate,
+    PredatorDeathRate,
+    OmnivoreReproductionRate,
+    OmnivoreMovingRate,
+    OmnivoreHuntingRate,
+    GrassRegrowthRate,
+    MutationRate,
+    MutationStd,
+    SeasonAmplitude,
+}
+
+impl ParamKey {
+    fn apply(self, config: &mut SimulationConfig, value: f32) {
+        let field = match self {
+            ParamKey::PreyReproductionRate => &mut config.prey_reproduction_rate,
+            ParamKey::PreyMovingRate => &mut config.prey_moving_rate,
+            ParamKey::PredatorReproductionRate => &mut config.predator_reproduction_rate,
+            ParamKey::PredatorMovingRate => &mut config.predator_moving_rate,
+            ParamKey::PredatorHuntingRate => &mut config.predator_hunting_rate,
+            ParamKey::PredatorDeathRate => &mut config.predator_death_rate,
+            ParamKey::OmnivoreReproductionRate => &mut config.omnivore_reproduction_rate,
+            ParamKey::OmnivoreMovingRate => &mut config.omnivore_moving_rate,
+            ParamKey::OmnivoreHuntingRate => &mut config.omnivore_hunting_rate,
+            ParamKey::GrassRegrowthRate => &mut config.grass_regrowth_rate,
+            ParamKey::MutationRate => &mut config.mutation_rate,
+            ParamKey::MutationStd => &mut config.mutation_std,
+            ParamKey::SeasonAmplitude => &mut config.season_amplitude,
+        };
+        *field = value;
+    }
+}
+
+/// Outcome of one run of a sweep.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepResult {
+    /// Value given to each varying field, in the order of the sweep.
+    pub params: Vec<(ParamKey, f32)>,
+    pub seed: u64,
+    /// State of the grid after the last step.
+    pub summary: SimSummary,
+    /// Events summed over all steps.
+    pub stats: StepStats,
+}
+
+/// Value given to each varying field of a run.
+type Params = Vec<(ParamKey, f32)>;
+
+/// Every combination of the varying values, the first field varying the slowest.
+fn combinations(varying: &[(ParamKey, Vec<f32>)]) -> Vec<Params> {
+    varying.iter().fold(vec![Vec::new()], |combos, (key, values)| {
+        combos
+            .iter()
+            .flat_map(|combo| {
+                values.iter().map(move |&value| {
+                    let mut combo = combo.clone();
+                    combo.push((*key, value));
+                    combo
+                })
+            })
+            .collect()
+    })
+}
+
+/// Config of each run of the sweep, with the values it was given.
+fn plan_runs(base: SimulationConfig, varying: &[(ParamKey, Vec<f32>)]) -> Result<Vec<(Params, SimulationConfig)>, ConfigError> {
+    combinations(varying)
+        .into_iter()
+        .map(|params| {
+            let mut config = base.clone();
+            for &(key, value) in &params {
+                key.apply(&mut config, value);
+            }
+            config.validate()?;
+            Ok((params, config))
+        })
+        .collect()
+}
+
+fn run_one(params: Params, config: SimulationConfig, steps: usize, seed: u64) -> SweepResult {
+    let mut sim = Simulation::with_seed(config, seed).expect("sweep configs are validated up front");
+    sim.init_grid();
+    sim.init_simulation();
+    let (_, stats) = sim.step_n(steps);
+    SweepResult { params, seed, summary: sim.summary(), stats }
+}
+
+/// Runs `base` for `steps` steps with each combination of the `varying` values.
+///
+/// Run `i` is seeded with `seed + i`, so a sweep is reproducible. Fails before running anything
+/// if a combination doesn't pass `SimulationConfig::validate`.
+pub fn run_sweep(
+    base: SimulationConfig,
+    varying: Vec<(ParamKey, Vec<f32>)>,
+    steps: usize,
+    seed: u64,
+) -> Result<Vec<SweepResult>, ConfigError> {
+    Ok(plan_runs(base, &varying)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, (params, config))| run_one(params, config, steps, seed.wrapping_add(i as u64)))
+        .collect())
+}
+
+/// Same as `run_sweep`, with the runs spread over the rayon thread pool. Runs share nothing, so
+/// the results are the same and in the same order.
+pub fn run_sweep_parallel(
+    base: SimulationConfig,
+    varying: Vec<(ParamKey, Vec<f32>)>,
+    steps: usize,
+    seed: u64,
+) -> Result<Vec<SweepResult>, ConfigError> {
+    Ok(plan_runs(base, &varying)?
+        .into_par_iter()
+        .enumerate()
+        .map(|(i, (params, config))| run_one(params, config, steps, seed.wrapping_add(i as u64)))
+        .collect())
+}
@@ -1,21 +1,33 @@
 #[cfg(test)]
 mod tests {
-    use life_game::simulation::{get_cell, Simulation};
+    use life_game::cell::CellKind;
+    use life_game::config::{BoundaryMode, ConfigError, InheritanceMode, InitialLayout, Neighbourhood, PredatorMortality, PreyIndex, ReproductionMode, SimulationConfig, Topology, UpdateMode};
+    use life_game::events::{Event, EventKind};
+    use life_game::run::{RunOutcome, StopCondition, StopReason};
+    use life_game::simulation::{get_cell, get_cell_ref, Simulation};
+    use life_game::stats::{StepStats, TraitKind};
+    use life_game::sweep::{run_sweep, run_sweep_parallel, ParamKey};
+    use pyo3::exceptions::PyZeroDivisionError;
+    use pyo3::types::{PyAnyMethods, PyModule, PyModuleMethods};
+    use pyo3::{Bound, Python};
+    use std::time::Duration;
 
     fn create_test_simulation() -> Simulation {
-        let mut sim = Simulation::new(
-            10,
-            10,
-            0.1,
-            0.1,
-            0.1,
-            0.1,
-            0.1,
-            0.1,
-            20, 
-            50,
-            50
-        );
+        let mut sim = Simulation::new(SimulationConfig {
+            width: 10,
+            height: 10,
+            prey_reproduction_rate: 0.1,
+            prey_moving_rate: 0.1,
+            predator_reproduction_rate: 0.1,
+            predator_moving_rate: 0.1,
+            predator_hunting_rate: 0.1,
+            predator_death_rate: 0.1,
+            predator_death_after: 20,
+            nb_prey_init: 50,
+            nb_predator_init: 50,
+            ..Default::default()
+        })
+        .unwrap();
         sim.init_grid();
         sim
     }
@@ -40,5 +52,2260 @@ mod tests {
         let cell = get_cell(&mut sim, 10, 10);
         assert!(cell.is_none());
     }
+
+    #[test]
+    fn get_cell_ref_matches_get_cell_bounds() {
+        let mut sim = create_test_simulation();
+        for (x, y) in [(5, 5), (0, 9), (-1, -1), (10, 10), (3, 10)] {
+            assert_eq!(get_cell_ref(&sim, x, y).is_some(), get_cell(&mut sim, x, y).is_some(), "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn cell_kind_within_bounds() {
+        let mut sim = create_test_simulation();
+        let kind = sim.cell_kind(5, 5);
+        assert!(kind.is_some());
+        assert!(sim.set_obstacle(5, 5));
+        assert_eq!(sim.cell_kind(5, 5), Some(CellKind::Obstacle));
+    }
+
+    #[test]
+    fn cell_kind_out_of_bounds_negative() {
+        let sim = create_test_simulation();
+        assert_eq!(sim.cell_kind(-1, -1), None);
+    }
+
+    #[test]
+    fn cell_kind_out_of_bounds_positive() {
+        let sim = create_test_simulation();
+        assert_eq!(sim.cell_kind(10, 10), None);
+    }
+
+    fn create_seeded_simulation(seed: u64) -> Simulation {
+        let config = SimulationConfig {
+            width: 20,
+            height: 20,
+            prey_reproduction_rate: 0.3,
+            predator_reproduction_rate: 0.3,
+            predator_death_rate: 0.05,
+            predator_death_after: 20,
+            nb_prey_init: 80,
+            nb_predator_init: 20,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, seed).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        sim
+    }
+
+    #[test]
+    fn same_seed_gives_same_trajectory() {
+        let mut a = create_seeded_simulation(42);
+        let mut b = create_seeded_simulation(42);
+        for _ in 0..50 {
+            assert_eq!(a.simulate(), b.simulate());
+            assert_eq!(a.get_nb_prey(), b.get_nb_prey());
+            assert_eq!(a.get_nb_predators(), b.get_nb_predators());
+        }
+    }
+
+    #[test]
+    fn neighbours_wrap_on_non_square_grid() {
+        let config = SimulationConfig {
+            width: 3,
+            height: 5,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+
+        let mut neighbours = sim.get_neighbours(0, 0).unwrap();
+        neighbours.sort();
+        neighbours.dedup();
+        assert_eq!(neighbours.len(), 8);
+        assert!(!neighbours.contains(&(0, 0)));
+
+        let neighbours = sim.get_neighbours(2, 4).unwrap();
+        assert!(neighbours.contains(&(0, 0)));
+    }
+
+    #[test]
+    fn grid_encodes_cell_content() {
+        let config = SimulationConfig {
+            width: 8,
+            height: 6,
+            nb_prey_init: 10,
+            nb_predator_init: 5,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 7).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+
+        let grid = sim.get_grid();
+        assert_eq!(grid.len(), 6);
+        assert!(grid.iter().all(|row| row.len() == 8));
+        assert!(grid.iter().flatten().all(|&v| v <= 2));
+        let nb_prey = grid.iter().flatten().filter(|&&v| v == 1).count();
+        let nb_predators = grid.iter().flatten().filter(|&&v| v == 2).count();
+        assert!(nb_prey <= 10 && nb_predators <= 5);
+        assert!(nb_prey + nb_predators > 0);
+    }
+
+    #[test]
+    fn fed_predator_does_not_starve() {
+        // On a 3x3 torus every cell neighbours every other one, so the predator always has prey in reach.
+        let config = SimulationConfig {
+            width: 3,
+            height: 3,
+            prey_reproduction_rate: 1.0,
+            predator_reproduction_rate: 0.0,
+            predator_hunting_rate: 1.0,
+            predator_death_rate: 0.0,
+            predator_death_after: 2,
+            nb_prey_init: 8,
+            nb_predator_init: 1,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 3).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let count = |sim: &Simulation, kind: u8| sim.get_grid().iter().flatten().filter(|&&v| v == kind).count();
+        for _ in 0..20 {
+            let had_prey = count(&sim, 1) > 0;
+            sim.simulate();
+            if had_prey {
+                assert_eq!(count(&sim, 2), 1);
+                assert!(sim.average_predator_hunger() < 2.0);
+            }
+        }
+    }
+
+    #[test]
+    fn fed_predator_never_dies_with_hunger_only_mortality() {
+        let config = SimulationConfig {
+            width: 3,
+            height: 3,
+            prey_reproduction_rate: 1.0,
+            predator_reproduction_rate: 0.0,
+            predator_hunting_rate: 1.0,
+            predator_death_rate: 1.0,
+            predator_death_after: 2,
+            predator_mortality: PredatorMortality::Hunger,
+            nb_prey_init: 8,
+            nb_predator_init: 1,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config.clone(), 3).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        for _ in 0..20 {
+            let had_prey = sim.get_nb_prey() > 0;
+            sim.simulate();
+            if had_prey {
+                assert_eq!(sim.get_nb_predators(), 1);
+            }
+        }
+
+        let mut sim = Simulation::with_seed(SimulationConfig { predator_mortality: PredatorMortality::Both, ..config }, 3).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        sim.simulate();
+        assert_eq!(sim.get_nb_predators(), 0);
+    }
+
+    #[test]
+    fn predators_starting_hungry_starve_within_two_steps() {
+        let config = SimulationConfig {
+            width: 8,
+            height: 8,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            predator_death_rate: 0.0,
+            predator_death_after: 10,
+            ..Default::default()
+        };
+        let predators_after_two_steps = |initial_hunger| {
+            let mut sim = Simulation::with_seed(SimulationConfig { predator_initial_hunger: initial_hunger, ..config.clone() }, 2).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            assert!(sim.spawn_predator(1, 1));
+            assert!(sim.spawn_predator(5, 6));
+            sim.simulate();
+            sim.simulate();
+            sim.summary().nb_predators
+        };
+        assert_eq!(predators_after_two_steps(Some(9)), 0);
+        assert_eq!(predators_after_two_steps(None), 2);
+    }
+
+    #[test]
+    fn moving_predators_starve_sooner() {
+        let config = SimulationConfig {
+            width: 8,
+            height: 8,
+            nb_prey_init: 0,
+            nb_predator_init: 1,
+            predator_reproduction_rate: 0.0,
+            predator_death_rate: 0.0,
+            predator_death_after: 10,
+            predator_initial_hunger: Some(0),
+            move_energy_cost: 1,
+            ..Default::default()
+        };
+        let predators_after_eight_steps = |moving_rate| {
+            let mut sim = Simulation::with_seed(SimulationConfig { predator_moving_rate: moving_rate, ..config.clone() }, 6).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            for _ in 0..8 {
+                sim.simulate();
+            }
+            sim.summary().nb_predators
+        };
+        assert_eq!(predators_after_eight_steps(1.0), 0);
+        assert_eq!(predators_after_eight_steps(0.0), 1);
+    }
+
+    #[test]
+    fn low_breeding_gate_needs_a_fresh_meal() {
+        let config = SimulationConfig {
+            width: 8,
+            height: 8,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            predator_death_rate: 0.0,
+            predator_reproduction_rate: 1.0,
+            predator_hunting_rate: 1.0,
+            predator_initial_hunger: Some(2),
+            reproduction_mode: ReproductionMode::Asexual,
+            ..Default::default()
+        };
+        let births_in_first_step = |gate, with_prey| {
+            let mut sim = Simulation::with_seed(SimulationConfig { predator_reproduce_below_hunger: gate, ..config.clone() }, 1).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            assert!(sim.spawn_predator(4, 4));
+            if with_prey {
+                assert!(sim.spawn_prey(5, 4));
+            }
+            sim.update().predators_born
+        };
+        assert_eq!(births_in_first_step(Some(1), false), 0);
+        assert_eq!(births_in_first_step(None, false), 1);
+        assert_eq!(births_in_first_step(Some(1), true), 1);
+    }
+
+    #[test]
+    fn fed_predator_skips_hunting_until_hungry() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            prey_reproduction_rate: 0.0,
+            prey_moving_rate: 0.0,
+            predator_reproduction_rate: 0.0,
+            predator_moving_rate: 0.0,
+            predator_hunting_rate: 1.0,
+            predator_death_rate: 0.0,
+            // Without a prey in sight the predator doesn't chase and stays in place.
+            predator_vision_radius: 0,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert!(sim.spawn_predator(2, 2));
+        assert!(sim.spawn_prey(1, 2));
+        assert!(sim.spawn_prey(3, 2));
+        assert_eq!(sim.update().prey_eaten, 1);
+        assert_eq!(sim.update().prey_eaten, 0);
+        assert_eq!(sim.average_predator_hunger(), 1.0);
+        assert_eq!(sim.update().prey_eaten, 1);
+    }
+
+    #[test]
+    fn offspring_wait_for_the_gestation_to_end() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            prey_reproduction_rate: 1.0,
+            prey_moving_rate: 0.0,
+            prey_gestation: 5,
+            reproduction_mode: ReproductionMode::Asexual,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert!(sim.spawn_prey(2, 2));
+        // Conceived at the first step, born five steps later.
+        for _ in 0..5 {
+            assert_eq!(sim.update().prey_born, 0);
+        }
+        assert_eq!(sim.update().prey_born, 1);
+        assert_eq!(sim.get_grid().iter().flatten().filter(|&&v| v == 1).count(), 2);
+    }
+
+    #[test]
+    fn grass_follows_the_seasons() {
+        // A prey on every cell eats one unit of grass per step while the grass regrows by the
+        // season factor: the stock shrinks in winter and recovers in summer.
+        let config = SimulationConfig {
+            width: 4,
+            height: 4,
+            prey_reproduction_rate: 0.0,
+            prey_moving_rate: 0.0,
+            prey_max_age: 1000,
+            grass_regrowth_rate: 1.0,
+            grass_max: 50.0,
+            season_length: 8,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        for x in 0..4 {
+            for y in 0..4 {
+                assert!(sim.spawn_prey(x, y));
+            }
+        }
+        assert!((sim.current_season_factor() - 1.0).abs() < 1e-6);
+        let mut totals = Vec::new();
+        for _ in 0..32 {
+            sim.update();
+            totals.push(sim.total_grass());
+        }
+        assert!((sim.current_season_factor() - 1.0).abs() < 1e-6);
+        for t in 8..24 {
+            assert!((totals[t] - totals[t + 8]).abs() < 1e-2);
+        }
+        let (min, max) = totals[8..16].iter().fold((f32::MAX, f32::MIN), |(lo, hi), &g| (lo.min(g), hi.max(g)));
+        assert!(max - min > 10.0);
+    }
+
+    #[test]
+    fn no_seasons_keep_a_constant_factor() {
+        let mut sim = create_seeded_simulation(0);
+        for _ in 0..5 {
+            assert_eq!(sim.current_season_factor(), 1.0);
+            sim.update();
+        }
+    }
+
+    #[test]
+    fn summary_of_even_populations_has_ln_2_diversity() {
+        let config = SimulationConfig {
+            width: 6,
+            height: 6,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.summary().shannon_diversity, 0.0);
+        for x in 0..3 {
+            assert!(sim.spawn_prey(x, 0));
+            assert!(sim.spawn_predator(x, 3));
+        }
+        let summary = sim.summary();
+        assert_eq!((summary.nb_prey, summary.nb_predators, summary.nb_omnivores), (3, 3, 0));
+        assert_eq!(summary.total_grass, sim.total_grass());
+        assert!((summary.shannon_diversity - std::f32::consts::LN_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn eaten_prey_leave_the_position_list() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            prey_reproduction_rate: 0.0,
+            prey_moving_rate: 0.0,
+            predator_reproduction_rate: 0.0,
+            predator_moving_rate: 0.0,
+            predator_hunting_rate: 1.0,
+            predator_death_rate: 0.0,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        // The prey is updated in the first phase, then eaten by the predator in the next one.
+        assert!(sim.spawn_prey(0, 0));
+        assert!(sim.spawn_predator(0, 1));
+        let (prey_position, predator_position) = sim.simulate();
+        assert_eq!(sim.summary().nb_prey, 0);
+        assert!(prey_position.is_empty());
+        assert_eq!(sim.get_nb_prey(), 0);
+        assert_eq!(predator_position.len(), 1);
+    }
+
+    #[test]
+    fn clear_removes_individuals_but_keeps_the_grid() {
+        let mut sim = create_seeded_simulation(1);
+        sim.simulate();
+        let grass = sim.total_grass();
+        sim.clear();
+        assert_eq!(sim.get_nb_prey(), 0);
+        assert_eq!(sim.get_nb_predators(), 0);
+        assert!(sim.get_grid().iter().flatten().all(|&v| v == 0));
+        assert_eq!(sim.total_grass(), grass);
+        assert!(get_cell(&mut sim, 0, 0).is_some());
+        assert!(sim.spawn_prey(0, 0));
+        assert_eq!(sim.get_nb_prey(), 1);
+    }
+
+    #[test]
+    fn prey_population_stays_under_its_cap() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            prey_reproduction_rate: 1.0,
+            reproduction_mode: ReproductionMode::Asexual,
+            max_prey: 10,
+            nb_prey_init: 3,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 4).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let mut stats = StepStats::default();
+        for _ in 0..30 {
+            stats += sim.update();
+            assert!(sim.summary().nb_prey <= 10);
+        }
+        assert_eq!(sim.summary().nb_prey, 10);
+        assert!(stats.births_suppressed > 0);
+    }
+
+    #[test]
+    fn individuals_age_once_per_step() {
+        // Moving individuals can land on cells of a later phase; they mustn't play twice.
+        let config = SimulationConfig {
+            width: 7,
+            height: 5,
+            prey_moving_rate: 1.0,
+            predator_moving_rate: 1.0,
+            nb_prey_init: 8,
+            nb_predator_init: 6,
+            ..Default::default()
+        };
+        for seed in 0..20 {
+            let mut sim = Simulation::with_seed(config.clone(), seed).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            sim.simulate();
+            // Newborns are 0 and everyone else aged by exactly one step.
+            assert!(sim.age_histogram().len() <= 2, "seed {seed}: {:?}", sim.age_histogram());
+        }
+    }
+
+    #[test]
+    fn init_places_exactly_the_requested_counts() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            nb_prey_init: 50,
+            nb_predator_init: 30,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 2).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.get_nb_prey(), 50);
+        assert_eq!(sim.get_nb_predators(), 30);
+    }
+
+    #[test]
+    fn boxed_in_prey_is_cornered() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            prey_flee_radius: 2,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        // The prey at (0, 0) plays first, surrounded by predators across the wrapping edges.
+        assert!(sim.spawn_prey(0, 0));
+        for (x, y) in [(1, 0), (4, 0), (0, 1), (0, 4), (1, 1), (1, 4), (4, 1), (4, 4)] {
+            assert!(sim.spawn_predator(x, y));
+        }
+        assert_eq!(sim.update().prey_cornered, 1);
+    }
+
+    #[test]
+    fn packed_prey_die_of_crowding_but_isolated_ones_do_not() {
+        let config = SimulationConfig {
+            width: 12,
+            height: 12,
+            prey_reproduction_rate: 0.0,
+            prey_moving_rate: 0.0,
+            crowding_death_rate: 1.0,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        // The middle of the 3x3 block has 8 prey around it, well beyond the threshold of 4.
+        for y in 1..4 {
+            for x in 1..4 {
+                assert!(sim.spawn_prey(x, y));
+            }
+        }
+        assert!(sim.spawn_prey(8, 8));
+        let stats = sim.update();
+        assert!(stats.prey_crowded > 0);
+        assert_eq!(sim.get_nb_prey(), 10 - stats.prey_crowded as usize);
+        assert_eq!(sim.cell_kind(8, 8), Some(CellKind::Prey));
+        for _ in 0..5 {
+            assert_eq!(sim.update().prey_crowded, 0);
+        }
+    }
+
+    #[test]
+    fn infection_does_not_spread_without_transmission() {
+        let config = SimulationConfig {
+            width: 20,
+            height: 20,
+            nb_prey_init: 200,
+            nb_predator_init: 0,
+            initial_infected_fraction: 0.25,
+            infection_duration: 1000,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config.clone(), 3).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.get_nb_infected(), 50);
+        for _ in 0..20 {
+            let before = sim.get_nb_infected();
+            assert_eq!(sim.update().prey_infected, 0);
+            assert!(sim.get_nb_infected() <= before);
+        }
+        let mut sim = Simulation::with_seed(SimulationConfig { infection_rate: 1.0, ..config }, 3).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let stats = sim.update();
+        assert!(stats.prey_infected > 0);
+    }
+
+    #[test]
+    fn central_cluster_packs_the_prey_around_the_centre() {
+        let config = SimulationConfig {
+            width: 30,
+            height: 30,
+            nb_prey_init: 50,
+            nb_predator_init: 20,
+            initial_layout: InitialLayout::CentralCluster,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 4).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let prey = sim.prey_positions();
+        assert_eq!(prey.len(), 50);
+        // A disc of radius 4 around (15, 15) holds 49 cells, the 50th prey lands just outside.
+        assert!(prey.iter().all(|&(x, y)| (x - 15).pow(2) + (y - 15).pow(2) <= 25), "{prey:?}");
+        assert_eq!(sim.get_nb_predators(), 20);
+    }
+
+    #[test]
+    fn doubling_the_hunger_increment_halves_the_survival_time() {
+        let survival = |hunger_increment| {
+            let config = SimulationConfig {
+                width: 5,
+                height: 5,
+                nb_prey_init: 0,
+                nb_predator_init: 0,
+                predator_death_after: 20,
+                predator_initial_hunger: Some(0),
+                predator_mortality: PredatorMortality::Hunger,
+                hunger_increment,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 0).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            assert!(sim.spawn_predator(2, 2));
+            let mut steps = 0;
+            while sim.get_nb_predators() > 0 {
+                sim.update();
+                steps += 1;
+            }
+            steps
+        };
+        assert_eq!(survival(1), 20);
+        assert_eq!(survival(2), 10);
+    }
+
+    #[test]
+    fn measured_rates_follow_the_births_and_deaths() {
+        let config = SimulationConfig {
+            width: 20,
+            height: 20,
+            prey_reproduction_rate: 1.0,
+            prey_moving_rate: 0.0,
+            reproduction_mode: ReproductionMode::Asexual,
+            predator_death_rate: 1.0,
+            predator_mortality: PredatorMortality::Random,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.measured_rates(5).steps, 0);
+        // The lone prey gives birth at once and the lone predator dies at once.
+        assert!(sim.spawn_prey(5, 5));
+        assert!(sim.spawn_predator(15, 15));
+        sim.update();
+        let rates = sim.measured_rates(1);
+        assert_eq!((rates.prey_birth_rate, rates.prey_death_rate), (1.0, 0.0));
+        assert_eq!((rates.predator_birth_rate, rates.predator_death_rate), (0.0, 1.0));
+        let (mut born, mut start) = (1, 1);
+        for _ in 0..3 {
+            start += sim.get_nb_prey();
+            born += sim.update().prey_born;
+        }
+        let rates = sim.measured_rates(10);
+        assert_eq!(rates.steps, 4);
+        assert_eq!(rates.prey_birth_rate, born as f32 / start as f32);
+        assert_eq!(rates.predator_birth_rate, 0.0);
+    }
+
+    #[test]
+    fn positions_list_spawned_individuals() {
+        let config = SimulationConfig {
+            width: 6,
+            height: 6,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert!(sim.spawn_prey(1, 4));
+        assert!(sim.spawn_prey(5, 0));
+        assert!(sim.spawn_predator(3, 3));
+        assert_eq!(sim.prey_positions(), vec![(1, 4), (5, 0)]);
+        assert_eq!(sim.predator_positions(), vec![(3, 3)]);
+    }
+
+    #[test]
+    fn run_for_runs_whole_steps_within_the_budget() {
+        let config = SimulationConfig {
+            width: 20,
+            height: 20,
+            record_history: true,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 4).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.run_for(Duration::ZERO), 0);
+        let steps = sim.run_for(Duration::from_millis(20));
+        assert!(steps > 0);
+        assert_eq!(sim.population_history().last().unwrap().0, steps);
+    }
+
+    #[test]
+    fn step_count_follows_simulate_reset_and_clear() {
+        let mut sim = create_test_simulation();
+        assert_eq!(sim.step_count(), 0);
+        for _ in 0..5 {
+            sim.simulate();
+        }
+        assert_eq!(sim.step_count(), 5);
+        sim.reset();
+        assert_eq!(sim.step_count(), 0);
+        sim.simulate();
+        sim.clear();
+        assert_eq!(sim.step_count(), 0);
+    }
+
+    #[test]
+    fn occupancy_is_the_fraction_of_occupied_cells() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 4,
+            nb_prey_init: 10,
+            nb_predator_init: 2,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.occupancy_by_species(), (0.25, 0.05));
+        assert!((sim.occupancy() - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn seeded_runs_pick_the_same_cells() {
+        let run = || {
+            let mut sim = Simulation::with_seed(SimulationConfig { width: 16, height: 16, ..Default::default() }, 77).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            (0..40).map(|_| {
+                sim.simulate();
+                sim.get_grid()
+            }).collect::<Vec<_>>()
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn loaded_grid_sets_the_cell_kinds() {
+        let mut sim = Simulation::new(SimulationConfig { width: 3, height: 3, ..Default::default() }).unwrap();
+        let pattern = vec![vec![1, 0, 2], vec![0, 3, 0], vec![1, 4, 1]];
+        sim.load_grid(pattern.clone()).unwrap();
+        assert_eq!(sim.get_grid(), pattern);
+        assert_eq!(sim.cell_kind(1, 2), Some(CellKind::Omnivore));
+        assert_eq!((sim.get_nb_prey(), sim.get_nb_predators(), sim.get_nb_omnivores()), (3, 1, 1));
+        assert_eq!(sim.get_neighbours(0, 0).unwrap().len(), 8);
+        assert!(sim.load_grid(vec![vec![0; 3]; 2]).is_err());
+        assert!(sim.load_grid(vec![vec![5, 0, 0], vec![0; 3], vec![0; 3]]).is_err());
+    }
+
+    #[test]
+    fn isolated_predators_migrate_to_the_prey_cluster() {
+        let config = SimulationConfig {
+            width: 20,
+            height: 20,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            migration_rate: 1.0,
+            predator_death_rate: 0.0,
+            predator_reproduction_rate: 0.0,
+            predator_moving_rate: 0.0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 5).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        for (x, y) in [(10, 10), (12, 10), (10, 12), (12, 12)] {
+            assert!(sim.spawn_prey(x, y));
+        }
+        assert!(sim.spawn_predator(0, 0));
+        assert!(sim.spawn_predator(18, 3));
+        sim.simulate();
+        let predators = sim.predator_positions();
+        assert_eq!(predators.len(), 2);
+        // Landed within one cell of the centroid (11, 11), then chased a prey for one more cell.
+        for (x, y) in predators {
+            assert!((x - 11).abs().max((y - 11).abs()) <= 2, "predator at ({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn cell_state_reports_the_spawned_individual() {
+        let config = SimulationConfig {
+            width: 6,
+            height: 6,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            predator_death_after: 20,
+            predator_hunting_rate: 0.7,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config.clone(), 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert!(sim.spawn_predator(2, 3));
+        assert!(sim.spawn_prey(4, 4));
+        let predator = sim.cell_state(2, 3).unwrap();
+        assert_eq!(predator.species, CellKind::Predator);
+        assert_eq!(predator.hunger, Some(10));
+        assert_eq!(predator.hunting_factor, Some(0.7));
+        let prey = sim.cell_state(4, 4).unwrap();
+        assert_eq!((prey.species, prey.energy, prey.hunger), (CellKind::Prey, Some(config.prey_max_energy), None));
+        assert!(sim.cell_state(0, 0).is_none());
+        assert!(sim.cell_state(6, 0).is_none());
+    }
+
+    #[test]
+    fn synchronous_update_keeps_freed_cells_until_the_next_step() {
+        // On a 3x1 strip the prey in the middle is updated first and steps left, freeing its cell
+        // for the prey on the right only in the asynchronous mode.
+        let run = |update_mode| {
+            let config = SimulationConfig {
+                width: 3,
+                height: 1,
+                boundary: BoundaryMode::Bounded,
+                nb_prey_init: 0,
+                nb_predator_init: 0,
+                prey_reproduction_rate: 0.0,
+                prey_moving_rate: 1.0,
+                update_mode,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 0).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            assert!(sim.spawn_prey(1, 0));
+            assert!(sim.spawn_prey(2, 0));
+            let stats = sim.update();
+            (sim.get_grid(), stats.blocked_moves)
+        };
+        assert_eq!(run(UpdateMode::Asynchronous), (vec![vec![1, 1, 0]], 0));
+        assert_eq!(run(UpdateMode::Synchronous), (vec![vec![1, 0, 1]], 1));
+    }
+
+    #[test]
+    fn synchronous_predator_misses_the_prey_that_just_moved_next_to_it() {
+        // On a 3x1 strip the prey at (0, 0), updated first, can only step right, next to the
+        // predator at (2, 0). Only in the asynchronous mode does the predator see it there.
+        let run = |update_mode| {
+            let config = SimulationConfig {
+                width: 3,
+                height: 1,
+                boundary: BoundaryMode::Bounded,
+                nb_prey_init: 0,
+                nb_predator_init: 0,
+                prey_reproduction_rate: 0.0,
+                prey_moving_rate: 1.0,
+                prey_flee_radius: 0,
+                predator_reproduction_rate: 0.0,
+                predator_hunting_rate: 1.0,
+                predator_death_rate: 0.0,
+                update_mode,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 0).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            assert!(sim.spawn_prey(0, 0));
+            assert!(sim.spawn_predator(2, 0));
+            let stats = sim.update();
+            (sim.get_grid(), stats.prey_eaten)
+        };
+        assert_eq!(run(UpdateMode::Asynchronous), (vec![vec![0, 2, 0]], 1));
+        assert_eq!(run(UpdateMode::Synchronous), (vec![vec![0, 1, 2]], 0));
+    }
+
+    #[test]
+    fn newborns_take_their_factors_from_the_inheritance_mode() {
+        let mean_moving_after_births = |inheritance| {
+            let config = SimulationConfig {
+                width: 20,
+                height: 20,
+                nb_prey_init: 40,
+                nb_predator_init: 0,
+                prey_moving_rate: 0.2,
+                prey_reproduction_rate: 0.5,
+                reproduction_mode: ReproductionMode::Asexual,
+                inheritance,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 9).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            sim.set_prey_moving_rate(0.8).unwrap();
+            let (_, stats) = sim.step_n(5);
+            assert!(stats.prey_born > 0);
+            sim.mean_trait(TraitKind::PreyMoving)
+        };
+        assert!((mean_moving_after_births(InheritanceMode::FromParent) - 0.2).abs() < 1e-6);
+        assert!(mean_moving_after_births(InheritanceMode::FromConfig) > 0.3);
+    }
+
+    #[test]
+    fn count_within_wraps_on_the_torus_only() {
+        let count_at_corner = |boundary| {
+            let config = SimulationConfig {
+                width: 5,
+                height: 5,
+                boundary,
+                nb_prey_init: 0,
+                nb_predator_init: 0,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 0).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            for (x, y) in [(4, 4), (0, 1), (1, 0), (0, 0)] {
+                assert!(sim.spawn_prey(x, y));
+            }
+            assert!(sim.spawn_predator(3, 0));
+            (
+                sim.count_species_within(0, 0, 1, CellKind::Prey),
+                sim.count_species_within(0, 0, 2, CellKind::Predator),
+                sim.count_species_within(0, 0, 1, CellKind::Empty),
+            )
+        };
+        assert_eq!(count_at_corner(BoundaryMode::Toroidal), (3, 1, 5));
+        assert_eq!(count_at_corner(BoundaryMode::Bounded), (2, 0, 1));
+    }
+
+    #[test]
+    fn count_within_does_not_count_a_cell_twice() {
+        let mut sim = Simulation::new(SimulationConfig { width: 3, height: 3, nb_prey_init: 9, ..Default::default() }).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.count_species_within(1, 1, 4, CellKind::Prey), 8);
+        assert_eq!(sim.count_species_within(3, 1, 1, CellKind::Prey), 0);
+    }
+
+    #[test]
+    fn density_peaks_at_the_cluster_centre() {
+        let density = |boundary, prey: &[(i32, i32)]| {
+            let config = SimulationConfig {
+                width: 7,
+                height: 7,
+                boundary,
+                nb_prey_init: 0,
+                nb_predator_init: 0,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 0).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            for &(x, y) in prey {
+                assert!(sim.spawn_prey(x, y));
+            }
+            sim.density_map(CellKind::Prey, 1)
+        };
+        let cluster = (2..=4).flat_map(|y| (2..=4).map(move |x| (x, y))).collect::<Vec<_>>();
+        let map = density(BoundaryMode::Toroidal, &cluster);
+        assert_eq!(map[3][3], 1.0);
+        assert_eq!(map[2][3], 5.0 / 8.0);
+        assert!(map.iter().flatten().filter(|&&d| d == 1.0).count() == 1);
+        // The bounded corner only has three neighbours.
+        let map = density(BoundaryMode::Bounded, &[(1, 0), (0, 1), (1, 1)]);
+        assert_eq!(map[0][0], 1.0);
+        assert_eq!(map[0][1], 2.0 / 5.0);
+    }
+
+    #[test]
+    fn grazing_herd_leaves_a_grass_dip() {
+        let config = SimulationConfig {
+            width: 20,
+            height: 20,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            prey_moving_rate: 0.0,
+            prey_reproduction_rate: 0.0,
+            graze_amount: 3.0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config.clone(), 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let herd = (8..12).flat_map(|y| (8..12).map(move |x| (x, y))).collect::<Vec<_>>();
+        for &(x, y) in &herd {
+            assert!(sim.spawn_prey(x, y));
+        }
+        sim.step_n(3);
+        let grass = sim.get_grass();
+        // Grazed 3 per step, regrown 1 per step once below the maximum.
+        for &(x, y) in &herd {
+            assert_eq!(grass[y as usize][x as usize], config.grass_max - 7.0);
+        }
+        assert_eq!(grass[0][0], config.grass_max);
+    }
+
+    #[test]
+    fn a_kill_logs_one_death_at_the_prey() {
+        let config = SimulationConfig {
+            width: 6,
+            height: 6,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            prey_moving_rate: 0.0,
+            prey_reproduction_rate: 0.0,
+            predator_hunting_rate: 1.0,
+            predator_death_rate: 0.0,
+            predator_reproduction_rate: 0.0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        sim.enable_event_log(100);
+        assert!(sim.spawn_prey(3, 2));
+        assert!(sim.spawn_predator(2, 2));
+        sim.simulate();
+        let events = sim.drain_events();
+        let deaths = events.iter().filter(|e| e.kind == EventKind::Death).collect::<Vec<_>>();
+        assert_eq!(deaths, [&Event { step: 1, kind: EventKind::Death, x: 3, y: 2, species: CellKind::Prey }]);
+        assert!(events.contains(&Event { step: 1, kind: EventKind::Kill, x: 2, y: 2, species: CellKind::Predator }));
+        assert!(sim.drain_events().is_empty());
+    }
+
+    #[test]
+    fn event_log_keeps_the_latest_events() {
+        let mut sim = create_test_simulation();
+        sim.init_simulation();
+        sim.simulate();
+        assert!(sim.drain_events().is_empty());
+        sim.enable_event_log(5);
+        sim.step_n(5);
+        let events = sim.drain_events();
+        assert_eq!(events.len(), 5);
+        assert!(events.iter().all(|e| e.step == 6));
+    }
+
+    #[test]
+    fn totals_and_ratio_follow_the_populations() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            nb_prey_init: 0,
+            nb_predator_init: 3,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config.clone(), 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.total_individuals(), 3);
+        assert_eq!(sim.predator_prey_ratio(), None);
+        let mut sim = Simulation::with_seed(SimulationConfig { nb_prey_init: 12, nb_omnivore_init: 2, ..config }, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.total_individuals(), 17);
+        assert_eq!(sim.predator_prey_ratio(), Some(0.25));
+    }
+
+    #[test]
+    fn diagonal_cells_are_never_moved_to_when_disabled() {
+        let moves = |allow_diagonal_moves: bool| {
+            let config = SimulationConfig {
+                width: 20,
+                height: 20,
+                nb_prey_init: 1,
+                nb_predator_init: 1,
+                prey_reproduction_rate: 0.0,
+                predator_reproduction_rate: 0.0,
+                prey_moving_rate: 1.0,
+                predator_moving_rate: 1.0,
+                predator_hunting_rate: 0.0,
+                predator_death_after: 1000,
+                prey_max_energy: 1000,
+                boundary: BoundaryMode::Bounded,
+                allow_diagonal_moves,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 5).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            let mut moves = Vec::new();
+            for _ in 0..40 {
+                let before = (sim.prey_positions(), sim.predator_positions());
+                sim.simulate();
+                let after = (sim.prey_positions(), sim.predator_positions());
+                // Zipping skips whoever died during the step.
+                for (a, b) in before.0.iter().zip(&after.0).chain(before.1.iter().zip(&after.1)) {
+                    moves.push(((b.0 - a.0).abs(), (b.1 - a.1).abs()));
+                }
+            }
+            moves
+        };
+        let diagonal = |&(dx, dy): &(i32, i32)| dx != 0 && dy != 0;
+        assert!(moves(true).iter().any(diagonal));
+        let axial = moves(false);
+        assert!(!axial.iter().any(diagonal));
+        assert!(axial.iter().any(|&step| step != (0, 0)));
+    }
+
+    #[test]
+    fn prey_starve_without_grass() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            prey_reproduction_rate: 0.0,
+            prey_moving_rate: 0.0,
+            grass_regrowth_rate: 0.0,
+            grass_max: 3.0,
+            nb_prey_init: 20,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 11).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.total_grass(), 300.0);
+        for _ in 0..50 {
+            sim.simulate();
+        }
+        assert!(sim.total_grass() < 300.0);
+        assert!(sim.get_grid().iter().flatten().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn prey_without_food_die_after_max_energy_steps() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            prey_reproduction_rate: 0.0,
+            grass_max: 0.0,
+            prey_max_energy: 5,
+            nb_prey_init: 20,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 5).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        sim.simulate();
+        assert!(sim.get_grid().iter().flatten().any(|&v| v == 1));
+        for _ in 1..5 {
+            sim.simulate();
+        }
+        assert!(sim.get_grid().iter().flatten().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn individuals_die_of_old_age() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            prey_reproduction_rate: 0.0,
+            predator_reproduction_rate: 0.0,
+            predator_hunting_rate: 0.0,
+            predator_death_rate: 0.0,
+            predator_death_after: 100,
+            prey_max_age: 3,
+            predator_max_age: 3,
+            nb_prey_init: 10,
+            nb_predator_init: 10,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 9).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let histogram = sim.age_histogram();
+        assert_eq!(histogram.len(), 1);
+        assert!(histogram[0] > 0);
+        for _ in 0..3 {
+            sim.simulate();
+        }
+        assert!(sim.age_histogram().is_empty());
+    }
+
+    #[test]
+    fn prey_flee_from_predators() {
+        let config = SimulationConfig {
+            width: 15,
+            height: 15,
+            prey_reproduction_rate: 0.0,
+            prey_moving_rate: 1.0,
+            prey_flee_radius: 100,
+            predator_reproduction_rate: 0.0,
+            predator_hunting_rate: 0.0,
+            predator_death_rate: 0.0,
+            predator_death_after: 100,
+            nb_prey_init: 1,
+            nb_predator_init: 1,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 1).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let find = |sim: &Simulation, kind: u8| {
+            for (y, row) in sim.get_grid().iter().enumerate() {
+                for (x, &v) in row.iter().enumerate() {
+                    if v == kind {
+                        return (x as i32, y as i32);
+                    }
+                }
+            }
+            panic!("no cell of kind {kind}");
+        };
+        let distance_sq = |a: (i32, i32), b: (i32, i32)| (a.0 - b.0).pow(2) + (a.1 - b.1).pow(2);
+        let mut nb_moves = 0;
+        for _ in 0..5 {
+            let prey = find(&sim, 1);
+            let predator = find(&sim, 2);
+            sim.simulate();
+            let new_prey = find(&sim, 1);
+            if new_prey != prey {
+                nb_moves += 1;
+                assert!(distance_sq(new_prey, predator) > distance_sq(prey, predator));
+            }
+        }
+        assert!(nb_moves > 0);
+    }
+
+    #[test]
+    fn parallel_and_sequential_updates_match() {
+        let create = |parallel: bool| {
+            let config = SimulationConfig {
+                width: 31,
+                height: 29,
+                prey_reproduction_rate: 0.3,
+                predator_reproduction_rate: 0.3,
+                predator_death_after: 20,
+                nb_prey_init: 200,
+                nb_predator_init: 50,
+                parallel,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 17).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            sim
+        };
+        let mut parallel = create(true);
+        let mut sequential = create(false);
+        // The same seed run again in parallel, contested cells must go the same way.
+        let mut repeated = create(true);
+        for _ in 0..30 {
+            parallel.simulate();
+            sequential.simulate();
+            repeated.simulate();
+            assert_eq!(parallel.get_nb_prey(), sequential.get_nb_prey());
+            assert_eq!(parallel.get_nb_predators(), sequential.get_nb_predators());
+            assert_eq!(parallel.get_grid(), sequential.get_grid());
+            assert_eq!(repeated.state_hash(), parallel.state_hash());
+        }
+    }
+
+    #[test]
+    fn every_predator_gets_a_target_when_prey_exist() {
+        let config = SimulationConfig {
+            width: 30,
+            height: 30,
+            nb_prey_init: 5,
+            nb_predator_init: 40,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 4).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        for _ in 0..5 {
+            let targets = sim.predator_targets();
+            assert!(!targets.is_empty());
+            if sim.get_grid().iter().flatten().any(|&v| v == 1) {
+                assert!(targets.iter().all(|(_, target)| target.is_some()));
+            }
+            sim.simulate();
+        }
+    }
+
+    #[test]
+    fn predators_target_their_true_nearest_prey() {
+        let config = SimulationConfig {
+            width: 40,
+            height: 40,
+            nb_prey_init: 6,
+            nb_predator_init: 3,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 21).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let mut prey = Vec::new();
+        for (y, row) in sim.get_grid().iter().enumerate() {
+            for (x, &v) in row.iter().enumerate() {
+                if v == 1 {
+                    prey.push((x as i32, y as i32));
+                }
+            }
+        }
+        // The grid wraps, so distances are measured the short way round.
+        let distance_sq = |a: (i32, i32), b: (i32, i32)| {
+            let (dx, dy) = ((a.0 - b.0).abs(), (a.1 - b.1).abs());
+            dx.min(40 - dx).pow(2) + dy.min(40 - dy).pow(2)
+        };
+        let targets = sim.predator_targets();
+        assert_eq!(targets.len(), 3);
+        for (predator, target) in targets {
+            let best = prey.iter().map(|&p| distance_sq(p, predator)).min().unwrap();
+            let target = target.unwrap();
+            assert!(prey.contains(&target));
+            assert_eq!(distance_sq(target, predator), best);
+        }
+    }
+
+    #[test]
+    fn prey_indexes_lead_to_the_same_run() {
+        let distance_sq = |a: (i32, i32), b: (i32, i32)| {
+            let (dx, dy) = ((a.0 - b.0).abs(), (a.1 - b.1).abs());
+            dx.min(40 - dx).pow(2) + dy.min(30 - dy).pow(2)
+        };
+        let mut sims = [PreyIndex::KdTree, PreyIndex::Grid].map(|prey_index| {
+            let config = SimulationConfig {
+                width: 40,
+                height: 30,
+                nb_prey_init: 150,
+                nb_predator_init: 30,
+                prey_index,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 8).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            sim
+        });
+        for _ in 0..10 {
+            let prey = sims[0].prey_positions();
+            let targets = sims[0].predator_targets();
+            for &(predator, target) in &targets {
+                let best = prey.iter().map(|&p| distance_sq(p, predator)).min();
+                assert_eq!(target.map(|target| distance_sq(target, predator)), best);
+            }
+            assert_eq!(sims[1].predator_targets(), targets);
+            assert_eq!(sims[0].simulate(), sims[1].simulate());
+        }
+    }
+
+    #[test]
+    fn spawned_predator_targets_the_closest_spawned_prey() {
+        let config = SimulationConfig {
+            width: 12,
+            height: 12,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            predator_vision_radius: 12,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert!(sim.spawn_prey(2, 2));
+        assert!(sim.spawn_prey(7, 6));
+        assert!(sim.spawn_predator(5, 5));
+        assert_eq!(sim.predator_targets(), vec![((5, 5), Some((7, 6)))]);
+    }
+
+    #[test]
+    fn reset_restarts_from_the_initial_population() {
+        let config = SimulationConfig {
+            width: 12,
+            height: 12,
+            nb_prey_init: 30,
+            nb_predator_init: 10,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 8).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        for _ in 0..10 {
+            sim.simulate();
+        }
+        sim.reset();
+        assert_eq!(sim.get_nb_prey(), 30);
+        assert_eq!(sim.get_nb_predators(), 10);
+        assert!(sim.age_histogram().len() == 1);
+        assert_eq!(sim.total_grass(), 12.0 * 12.0 * 10.0);
+        assert_eq!(sim.get_neighbours(5, 5).unwrap().len(), 8);
+        let grid = sim.get_grid();
+        let nb_prey = grid.iter().flatten().filter(|&&v| v == 1).count();
+        let nb_predators = grid.iter().flatten().filter(|&&v| v == 2).count();
+        assert_eq!((nb_prey, nb_predators), (30, 10));
+    }
+
+    #[test]
+    fn single_kill_is_counted() {
+        // On a 3x3 torus the predator is adjacent to the prey wherever they are.
+        let config = SimulationConfig {
+            width: 3,
+            height: 3,
+            prey_reproduction_rate: 0.0,
+            prey_moving_rate: 0.0,
+            predator_reproduction_rate: 0.0,
+            predator_hunting_rate: 1.0,
+            predator_death_rate: 0.0,
+            nb_prey_init: 1,
+            nb_predator_init: 1,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 2).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let count = |sim: &Simulation, kind: u8| sim.get_grid().iter().flatten().filter(|&&v| v == kind).count();
+        assert_eq!(count(&sim, 1), 1);
+        assert_eq!(count(&sim, 2), 1);
+        let stats = sim.update();
+        assert_eq!(stats, StepStats { prey_eaten: 1, ..Default::default() });
+        assert_eq!(count(&sim, 1), 0);
+        assert_eq!(sim.update(), StepStats::default());
+    }
+
+    #[test]
+    fn render_png_draws_one_block_per_cell() {
+        let config = SimulationConfig {
+            width: 4,
+            height: 3,
+            nb_prey_init: 3,
+            nb_predator_init: 3,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 5).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let path = std::env::temp_dir().join("life_game_render_png_test.png");
+        let path = path.to_str().unwrap();
+        assert!(sim.render_png(path, 0).is_err());
+        sim.render_png(path, 2).unwrap();
+
+        let image = image::open(path).unwrap().to_rgb8();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(image.dimensions(), (8, 6));
+        for (y, row) in sim.get_grid().iter().enumerate() {
+            for (x, &kind) in row.iter().enumerate() {
+                let expected = match kind {
+                    1 => [0, 200, 0],
+                    2 => [220, 0, 0],
+                    _ => [255, 255, 255],
+                };
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    assert_eq!(image.get_pixel(2 * x as u32 + dx, 2 * y as u32 + dy).0, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ascii_matches_grid() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 3,
+            nb_prey_init: 4,
+            nb_predator_init: 4,
+            nb_omnivore_init: 2,
+            obstacle_fraction: 0.2,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 11).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let grid = sim.get_grid();
+        for kind in 0..=4 {
+            assert!(grid.iter().flatten().any(|&v| v == kind), "no cell of kind {kind}");
+        }
+        let expected: String = grid
+            .iter()
+            .map(|row| {
+                let mut line: String = row.iter().map(|&v| ['.', 'o', 'X', '#', '@'][v as usize]).collect();
+                line.push('\n');
+                line
+            })
+            .collect();
+        assert_eq!(sim.to_ascii(), expected);
+        assert_eq!(sim.to_ascii().lines().count(), 3);
+    }
+
+    #[test]
+    fn population_history_records_every_step() {
+        let config = SimulationConfig {
+            width: 20,
+            height: 20,
+            nb_prey_init: 60,
+            nb_predator_init: 30,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config.clone(), 4).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        for _ in 0..5 {
+            sim.update();
+        }
+        let history = sim.population_history();
+        assert_eq!(history.iter().map(|&(step, _, _)| step).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        let grid = sim.get_grid();
+        let count = |kind: u8| grid.iter().flatten().filter(|&&v| v == kind).count();
+        assert_eq!(history[4], (5, count(1), count(2)));
+
+        let mut sim = Simulation::with_seed(SimulationConfig { record_history: false, ..config }, 4).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        sim.update();
+        assert!(sim.population_history().is_empty());
+    }
+
+    #[test]
+    fn history_csv_has_header_and_one_line_per_step() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            nb_prey_init: 20,
+            nb_predator_init: 10,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 8).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let path = std::env::temp_dir().join("life_game_history_csv_test.csv");
+        let path = path.to_str().unwrap();
+
+        sim.export_history_csv(path).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "step,prey,predators\n");
+
+        sim.update();
+        sim.update();
+        sim.export_history_csv(path).unwrap();
+        let csv = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let expected: String = std::iter::once("step,prey,predators\n".to_string())
+            .chain(sim.population_history().iter().map(|(s, p, q)| format!("{s},{p},{q}\n")))
+            .collect();
+        assert_eq!(csv, expected);
+        assert_eq!(csv.lines().count(), 3);
+    }
+
+    #[test]
+    fn run_to_csv_writes_the_initial_state_and_every_step() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            nb_prey_init: 20,
+            nb_predator_init: 10,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config.clone(), 8).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let path = std::env::temp_dir().join("life_game_run_to_csv_test.csv");
+        let path = path.to_str().unwrap();
+
+        sim.run_to_csv(5, path).unwrap();
+        let csv = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "step,prey,predators");
+        assert_eq!(lines.len(), 1 + 5 + 1);
+        assert_eq!(lines[1], "0,20,10");
+        assert_eq!(sim.step_count(), 5);
+
+        let mut replay = Simulation::with_seed(config, 8).unwrap();
+        replay.init_grid();
+        replay.init_simulation();
+        for line in &lines[2..] {
+            replay.update();
+            assert_eq!(*line, format!("{},{},{}", replay.step_count(), replay.get_nb_prey(), replay.get_nb_predators()));
+        }
+    }
+
+    #[test]
+    fn run_until_stops_at_once_without_prey() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            nb_prey_init: 0,
+            nb_predator_init: 100,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 1).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let outcome = sim.run_until(50, StopCondition::PreyExtinct);
+        assert_eq!(outcome, RunOutcome { step: 0, reason: StopReason::ConditionMet });
+    }
+
+    #[test]
+    fn run_until_detects_starved_predators() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            predator_reproduction_rate: 0.0,
+            predator_death_rate: 0.0,
+            predator_death_after: 4,
+            nb_prey_init: 0,
+            nb_predator_init: 30,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 1).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let outcome = sim.run_until(50, StopCondition::PredatorsExtinct);
+        assert_eq!(outcome.reason, StopReason::ConditionMet);
+        assert!(outcome.step > 0 && outcome.step <= 4);
+        assert!(sim.get_grid().iter().flatten().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn run_until_reports_max_steps() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 1).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let stable = StopCondition::Stable { window: 3, tolerance: 0 };
+        assert_eq!(sim.run_until(2, stable), RunOutcome { step: 2, reason: StopReason::MaxSteps });
+        sim.reset();
+        assert_eq!(sim.run_until(10, stable), RunOutcome { step: 3, reason: StopReason::ConditionMet });
+    }
+
+    #[test]
+    fn radius_two_links_24_neighbours() {
+        let config = SimulationConfig {
+            width: 20,
+            height: 20,
+            neighbourhood_radius: 2,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let neighbours = sim.get_neighbours(0, 10).unwrap();
+        assert_eq!(neighbours.len(), 24);
+        assert!(neighbours.contains(&(18, 8)));
+        assert!(neighbours.contains(&(2, 12)));
+        assert!(!neighbours.contains(&(0, 10)));
+    }
+
+    #[test]
+    fn parallel_and_sequential_updates_match_with_radius_two() {
+        let config = SimulationConfig {
+            width: 31,
+            height: 27,
+            neighbourhood_radius: 2,
+            nb_prey_init: 200,
+            nb_predator_init: 100,
+            ..Default::default()
+        };
+        let mut par = Simulation::with_seed(config.clone(), 6).unwrap();
+        let mut seq = Simulation::with_seed(SimulationConfig { parallel: false, ..config }, 6).unwrap();
+        for sim in [&mut par, &mut seq] {
+            sim.init_grid();
+            sim.init_simulation();
+        }
+        for _ in 0..5 {
+            assert_eq!(par.update(), seq.update());
+            assert_eq!(par.get_grid(), seq.get_grid());
+        }
+    }
+
+    #[test]
+    fn moore_links_the_8_surrounding_cells() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let mut neighbours = get_cell_ref(&sim, 4, 6).unwrap().neighbour_positions();
+        assert_eq!(sim.get_neighbours(4, 6), Some(neighbours.clone()));
+        neighbours.sort();
+        assert_eq!(neighbours, vec![(3, 5), (3, 6), (3, 7), (4, 5), (4, 7), (5, 5), (5, 6), (5, 7)]);
+    }
+
+    #[test]
+    fn hex_cells_have_6_neighbours() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            topology: Topology::Hex,
+            boundary: BoundaryMode::Bounded,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        for y in 1..9 {
+            for x in 1..9 {
+                assert_eq!(sim.get_neighbours(x, y).unwrap().len(), 6, "({x}, {y})");
+            }
+        }
+        // Odd rows are shifted half a cell right of even ones.
+        let mut even = sim.get_neighbours(4, 6).unwrap();
+        even.sort();
+        assert_eq!(even, vec![(3, 5), (3, 6), (3, 7), (4, 5), (4, 7), (5, 6)]);
+        let mut odd = sim.get_neighbours(4, 5).unwrap();
+        odd.sort();
+        assert_eq!(odd, vec![(3, 5), (4, 4), (4, 6), (5, 4), (5, 5), (5, 6)]);
+    }
+
+    #[test]
+    fn von_neumann_links_4_neighbours() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            neighbourhood: Neighbourhood::VonNeumann,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let mut neighbours = sim.get_neighbours(5, 5).unwrap();
+        neighbours.sort();
+        assert_eq!(neighbours, vec![(4, 5), (5, 4), (5, 6), (6, 5)]);
+    }
+
+    #[test]
+    fn bounded_corner_has_3_neighbours() {
+        let config = SimulationConfig {
+            width: 6,
+            height: 4,
+            boundary: BoundaryMode::Bounded,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let mut corner = sim.get_neighbours(5, 3).unwrap();
+        corner.sort();
+        assert_eq!(corner, vec![(4, 2), (4, 3), (5, 2)]);
+        assert_eq!(sim.get_neighbours(2, 0).unwrap().len(), 5);
+        assert_eq!(sim.get_neighbours(2, 2).unwrap().len(), 8);
+    }
+
+    #[test]
+    fn walled_in_prey_neither_move_nor_breed() {
+        let config = SimulationConfig {
+            width: 3,
+            height: 3,
+            prey_reproduction_rate: 1.0,
+            prey_moving_rate: 1.0,
+            nb_prey_init: 2,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 3).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let grid = sim.get_grid();
+        assert_eq!(grid.iter().flatten().filter(|&&v| v == 1).count(), 2);
+        for (y, row) in grid.iter().enumerate() {
+            for (x, &kind) in row.iter().enumerate() {
+                if kind == 0 {
+                    assert!(sim.set_obstacle(x as i32, y as i32));
+                }
+            }
+        }
+        let walled = sim.get_grid();
+        assert_eq!(walled.iter().flatten().filter(|&&v| v == 3).count(), 7);
+        for _ in 0..3 {
+            let stats = sim.update();
+            assert_eq!(stats.prey_born, 0);
+            assert_eq!(sim.get_grid(), walled);
+        }
+        assert!(!sim.set_obstacle(3, 0));
+    }
+
+    #[test]
+    fn traits_are_inherited_and_drift_with_mutation() {
+        let config = SimulationConfig {
+            width: 30,
+            height: 30,
+            nb_prey_init: 200,
+            nb_predator_init: 50,
+            mutation_rate: 1.0,
+            mutation_std: 0.0,
+            ..Default::default()
+        };
+        let run = |config: SimulationConfig| {
+            let mut sim = Simulation::with_seed(config, 12).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            let mut born = 0;
+            for _ in 0..10 {
+                born += sim.update().prey_born;
+            }
+            assert!(born > 0);
+            sim
+        };
+
+        let sim = run(config.clone());
+        assert_eq!(sim.mean_trait(TraitKind::PreyReproduction), 0.5);
+        assert_eq!(sim.mean_trait(TraitKind::PreyMoving), 0.5);
+
+        let sim = run(SimulationConfig { mutation_std: 0.2, ..config });
+        assert_ne!(sim.mean_trait(TraitKind::PreyReproduction), 0.5);
+        assert_ne!(sim.mean_trait(TraitKind::PreyMoving), 0.5);
+    }
+
+    #[test]
+    fn predators_ignore_prey_beyond_their_vision() {
+        let config = SimulationConfig {
+            width: 40,
+            height: 40,
+            nb_prey_init: 1,
+            nb_predator_init: 1,
+            predator_vision_radius: 3,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config.clone(), 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let targets = sim.predator_targets();
+        assert_eq!(targets.len(), 1);
+        let grid = sim.get_grid();
+        let prey = (0..40)
+            .flat_map(|y| (0..40).map(move |x| (x, y)))
+            .find(|&(x, y)| grid[y as usize][x as usize] == 1)
+            .unwrap();
+        let (predator, target) = targets[0];
+        assert!((prey.0 - predator.0).abs().max((prey.1 - predator.1).abs()) > 3);
+        assert_eq!(target, None);
+
+        let mut sim = Simulation::with_seed(SimulationConfig { predator_vision_radius: 40, ..config }, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert_eq!(sim.predator_targets(), vec![(predator, Some(prey))]);
+    }
+
+    #[test]
+    fn spawn_places_individuals_on_free_cells_only() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert!(sim.spawn_prey(1, 2));
+        assert!(sim.spawn_predator(3, 4));
+        assert!(!sim.spawn_predator(1, 2));
+        assert!(!sim.spawn_prey(3, 4));
+        assert!(!sim.spawn_prey(5, 0));
+        assert!(!sim.spawn_predator(0, -1));
+        assert!(sim.set_obstacle(0, 0));
+        assert!(!sim.spawn_prey(0, 0));
+        assert_eq!(sim.get_nb_prey(), 1);
+        assert_eq!(sim.get_nb_predators(), 1);
+        let grid = sim.get_grid();
+        assert_eq!(grid[2][1], 1);
+        assert_eq!(grid[4][3], 2);
+    }
+
+    #[test]
+    fn out_of_range_rate_is_rejected() {
+        let config = SimulationConfig {
+            prey_reproduction_rate: 5.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::RateOutOfRange { field: "prey_reproduction_rate", value: 5.0 })
+        );
+        assert!(Simulation::new(config).is_err());
+        assert_eq!(SimulationConfig::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn zero_width_is_rejected() {
+        let config = SimulationConfig {
+            width: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::NonPositiveDimension { field: "width", value: 0 })
+        );
+        assert!(Simulation::new(config).is_err());
+    }
+
+    #[test]
+    fn negative_radii_and_inverted_bounds_are_rejected() {
+        let config = SimulationConfig {
+            neighbourhood_radius: -1,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::RadiusTooSmall { field: "neighbourhood_radius", value: -1, min: 1 })
+        );
+        assert!(Simulation::with_seed(config, 0).is_err());
+        let config = SimulationConfig {
+            predator_vision_radius: -3,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::RadiusTooSmall { field: "predator_vision_radius", value: -3, min: 0 })
+        );
+        let config = SimulationConfig {
+            prey_reproduction_min_neighbours: 5,
+            prey_reproduction_max_neighbours: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::InvertedBounds {
+                min_field: "prey_reproduction_min_neighbours",
+                min: 5,
+                max_field: "prey_reproduction_max_neighbours",
+                max: 2,
+            })
+        );
+        assert!(Simulation::with_seed(config, 0).is_err());
+    }
+
+    #[test]
+    fn odd_height_hex_torus_is_rejected() {
+        let config = SimulationConfig {
+            height: 9,
+            topology: Topology::Hex,
+            ..Default::default()
+        };
+        assert_eq!(config.validate(), Err(ConfigError::OddHexTorusHeight { height: 9 }));
+        assert!(Simulation::new(config.clone()).is_err());
+        let bounded = SimulationConfig { boundary: BoundaryMode::Bounded, ..config };
+        assert_eq!(bounded.validate(), Ok(()));
+    }
+
+    #[test]
+    fn step_n_sums_the_events_of_each_step() {
+        let mut batched = create_seeded_simulation(21);
+        let mut stepped = create_seeded_simulation(21);
+        let mut total = StepStats::default();
+        for _ in 0..6 {
+            total += stepped.update();
+        }
+        assert_eq!(batched.step_n(6), (6, total));
+        assert_eq!(batched.get_grid(), stepped.get_grid());
+    }
+
+    #[test]
+    fn step_n_stops_on_the_stop_condition() {
+        let config = SimulationConfig {
+            width: 10,
+            height: 10,
+            predator_reproduction_rate: 0.0,
+            predator_death_rate: 0.0,
+            predator_death_after: 4,
+            nb_prey_init: 0,
+            nb_predator_init: 30,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 1).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        let nb_predators = sim.get_grid().iter().flatten().filter(|&&v| v == 2).count() as u32;
+        sim.set_stop_condition(Some(StopCondition::PredatorsExtinct));
+        let (steps, stats) = sim.step_n(50);
+        assert!(steps > 0 && steps <= 4);
+        assert_eq!(stats.predators_starved, nb_predators);
+    }
+
+    #[test]
+    fn prey_contending_for_one_cell_both_survive() {
+        // (0, 0) and (2, 0) only share (1, 0) as a neighbour in a bounded 3x1 box.
+        let config = SimulationConfig {
+            width: 3,
+            height: 1,
+            boundary: BoundaryMode::Bounded,
+            prey_reproduction_rate: 0.0,
+            prey_moving_rate: 1.0,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert!(sim.spawn_prey(0, 0));
+        assert!(sim.spawn_prey(2, 0));
+        let mut blocked = 0;
+        for _ in 0..5 {
+            blocked += sim.update().blocked_moves;
+            assert_eq!(sim.get_grid()[0].iter().filter(|&&v| v == 1).count(), 2);
+        }
+        assert!(blocked > 0);
+    }
+
+    #[test]
+    fn sexual_reproduction_needs_a_partner() {
+        let config = SimulationConfig {
+            width: 7,
+            height: 7,
+            prey_reproduction_rate: 1.0,
+            prey_moving_rate: 0.0,
+            predator_reproduction_rate: 1.0,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            reproduction_mode: ReproductionMode::Sexual,
+            ..Default::default()
+        };
+        let births = |config: &SimulationConfig, prey: &[(i32, i32)]| {
+            let mut sim = Simulation::with_seed(config.clone(), 0).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            for &(x, y) in prey {
+                assert!(sim.spawn_prey(x, y));
+            }
+            sim.update().prey_born
+        };
+        assert_eq!(births(&config, &[(3, 3)]), 0);
+        assert!(births(&config, &[(3, 3), (4, 3)]) > 0);
+
+        let asexual = SimulationConfig { reproduction_mode: ReproductionMode::Asexual, ..config };
+        assert!(births(&asexual, &[(3, 3)]) > 0);
+    }
+
+    #[test]
+    fn omnivores_outlive_predators_on_grass() {
+        let config = SimulationConfig {
+            width: 5,
+            height: 5,
+            predator_moving_rate: 0.0,
+            predator_reproduction_rate: 0.0,
+            predator_death_rate: 0.0,
+            omnivore_moving_rate: 0.0,
+            omnivore_reproduction_rate: 0.0,
+            nb_prey_init: 0,
+            nb_predator_init: 0,
+            ..Default::default()
+        };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        sim.init_grid();
+        sim.init_simulation();
+        assert!(sim.spawn_predator(0, 0));
+        assert!(sim.spawn_omnivore(2, 2));
+        assert!(!sim.spawn_omnivore(0, 0));
+        let mut stats = StepStats::default();
+        for _ in 0..50 {
+            stats += sim.update();
+        }
+        assert_eq!(stats.predators_starved, 1);
+        assert_eq!(stats.omnivores_starved, 0);
+        assert_eq!(sim.cell_kind(0, 0), Some(CellKind::Empty));
+        assert_eq!(sim.cell_kind(2, 2), Some(CellKind::Omnivore));
+        assert_eq!(sim.get_nb_omnivores(), 1);
+    }
+
+    #[test]
+    fn config_rates_are_readable_and_validated_on_set() {
+        let config = SimulationConfig { width: 7, prey_reproduction_rate: 0.4, ..Default::default() };
+        let mut sim = Simulation::with_seed(config, 0).unwrap();
+        assert_eq!(sim.get_width(), 7);
+        assert_eq!(sim.prey_reproduction_rate(), 0.4);
+        assert!(sim.set_prey_reproduction_rate(0.9).is_ok());
+        assert_eq!(sim.prey_reproduction_rate(), 0.9);
+        assert_eq!(
+            sim.set_prey_reproduction_rate(1.5),
+            Err(ConfigError::RateOutOfRange { field: "prey_reproduction_rate", value: 1.5 })
+        );
+        assert_eq!(sim.prey_reproduction_rate(), 0.9);
+    }
+
+    #[test]
+    fn callback_stops_the_run_and_propagates_errors() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut sim = create_seeded_simulation(3);
+            let stop_at_three = py.eval(c"lambda step, prey, predators: step < 3", None, None).unwrap();
+            assert_eq!(sim.run_with_callback(py, 10, stop_at_three.unbind()).unwrap(), 3);
+            let failing = py.eval(c"lambda step, prey, predators: 1 / 0", None, None).unwrap();
+            let err = sim.run_with_callback(py, 10, failing.unbind()).unwrap_err();
+            assert!(err.is_instance_of::<PyZeroDivisionError>(py));
+        });
+    }
+
+    #[test]
+    fn capped_runs_stay_under_the_cap_with_or_without_parallelism() {
+        // The prey grow from 400 towards the cap: the phases far from it run their rows
+        // concurrently, the last ones before it one after the other.
+        let run = |parallel: bool, max_prey: u32| {
+            let config = SimulationConfig {
+                width: 40,
+                height: 40,
+                prey_reproduction_rate: 0.5,
+                nb_prey_init: 400,
+                nb_predator_init: 0,
+                max_prey,
+                parallel,
+                ..Default::default()
+            };
+            let mut sim = Simulation::with_seed(config, 12).unwrap();
+            sim.init_grid();
+            sim.init_simulation();
+            (0..15)
+                .map(|_| {
+                    sim.simulate();
+                    (sim.get_nb_prey(), sim.state_hash())
+                })
+                .collect::<Vec<_>>()
+        };
+        // Without the cap, the prey go over it.
+        assert!(run(false, 0).iter().any(|&(prey, _)| prey > 1000));
+        let serial = run(false, 1000);
+        assert!(serial.iter().all(|&(prey, _)| prey <= 1000), "{serial:?}");
+        assert_eq!(serial.last().unwrap().0, 1000, "{serial:?}");
+        assert_eq!(run(true, 1000), serial);
+    }
+
+    #[test]
+    fn pickled_simulation_continues_identically() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "life_game").unwrap();
+            module.add_class::<Simulation>().unwrap();
+            py.import("sys").unwrap().getattr("modules").unwrap().set_item("life_game", module).unwrap();
+            let pickle = py.import("pickle").unwrap();
+            let sim = Bound::new(py, create_seeded_simulation(5)).unwrap();
+            sim.borrow_mut().step_n(20);
+            let state = pickle.call_method1("dumps", (&sim,)).unwrap();
+            let restored = pickle.call_method1("loads", (state,)).unwrap().downcast_into::<Simulation>().unwrap();
+            for _ in 0..20 {
+                sim.borrow_mut().update();
+                restored.borrow_mut().update();
+                assert_eq!(sim.borrow().get_nb_prey(), restored.borrow().get_nb_prey());
+                assert_eq!(sim.borrow().get_nb_predators(), restored.borrow().get_nb_predators());
+            }
+            assert_eq!(sim.borrow().get_grid(), restored.borrow().get_grid());
+        });
+    }
+
+    #[test]
+    fn state_hash_survives_pickling_and_changes_with_a_step() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "life_game").unwrap();
+            module.add_class::<Simulation>().unwrap();
+            py.import("sys").unwrap().getattr("modules").unwrap().set_item("life_game", module).unwrap();
+            let pickle = py.import("pickle").unwrap();
+            let sim = Bound::new(py, create_seeded_simulation(5)).unwrap();
+            sim.borrow_mut().step_n(10);
+            let state = pickle.call_method1("dumps", (&sim,)).unwrap();
+            let restored = pickle.call_method1("loads", (state,)).unwrap().downcast_into::<Simulation>().unwrap();
+            let hash = sim.borrow().state_hash();
+            assert_eq!(restored.borrow().state_hash(), hash);
+            assert_eq!(sim.borrow().state_hash(), hash);
+            restored.borrow_mut().update();
+            assert_ne!(restored.borrow().state_hash(), hash);
+            sim.borrow_mut().update();
+            assert_eq!(sim.borrow().state_hash(), restored.borrow().state_hash());
+        });
+    }
+
+    #[test]
+    fn restoring_a_snapshot_makes_an_independent_copy() {
+        let mut source = create_seeded_simulation(5);
+        source.step_n(10);
+        let hash = source.state_hash();
+        let mut snapshot = source.snapshot();
+        let mut copy = create_seeded_simulation(6);
+        assert!(copy.restore(&snapshot));
+        assert_eq!(copy.state_hash(), hash);
+        copy.step_n(5);
+        copy.clear();
+        snapshot.cells.fill(CellKind::Empty);
+        assert_eq!(source.state_hash(), hash);
+        // The generator comes along, so a restored copy steps exactly like its source.
+        let mut replay = create_seeded_simulation(6);
+        assert!(replay.restore(&source.snapshot()));
+        source.update();
+        replay.update();
+        assert_eq!(replay.state_hash(), source.state_hash());
+        assert!(!Simulation::new(SimulationConfig { width: 3, height: 3, ..Default::default() }).unwrap().restore(&snapshot));
+    }
+
+    #[test]
+    fn running_a_clone_leaves_the_original_alone() {
+        let mut original = create_seeded_simulation(5);
+        original.step_n(10);
+        let populations = (original.get_nb_prey(), original.get_nb_predators());
+        let hash = original.state_hash();
+        let mut fork = original.clone();
+        assert_eq!(fork.state_hash(), hash);
+        fork.step_n(20);
+        assert_eq!((original.get_nb_prey(), original.get_nb_predators()), populations);
+        assert_eq!(original.state_hash(), hash);
+        let mut twin = original.clone();
+        original.update();
+        twin.update();
+        assert_eq!(twin.state_hash(), original.state_hash());
+    }
+
+    #[test]
+    fn iter_steps_yields_the_populations_after_each_step() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let sim = Bound::new(py, create_seeded_simulation(5)).unwrap();
+            let steps = sim
+                .call_method1("iter_steps", (5,))
+                .unwrap()
+                .try_iter()
+                .unwrap()
+                .map(|item| item.unwrap().extract::<(usize, usize)>().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(steps.len(), 5);
+            assert_eq!(sim.borrow().step_count(), 5);
+            assert_eq!(steps[4], (sim.borrow().get_nb_prey(), sim.borrow().get_nb_predators()));
+            sim.borrow_mut().clear();
+            let mut stepper = sim.call_method1("iter_steps", (5,)).unwrap().try_iter().unwrap();
+            assert!(stepper.next().is_none());
+            assert_eq!(sim.borrow().step_count(), 0);
+        });
+    }
+
+    #[test]
+    fn sweep_runs_every_combination() {
+        let base = SimulationConfig {
+            width: 10,
+            height: 10,
+            nb_prey_init: 20,
+            nb_predator_init: 10,
+            ..Default::default()
+        };
+        let varying = vec![
+            (ParamKey::PreyReproductionRate, vec![0.2, 0.6]),
+            (ParamKey::PredatorReproductionRate, vec![0.1, 0.3]),
+        ];
+        let results = run_sweep(base.clone(), varying.clone(), 5, 42).unwrap();
+        let params = results.iter().map(|r| r.params.clone()).collect::<Vec<_>>();
+        assert_eq!(params, vec![
+            vec![(ParamKey::PreyReproductionRate, 0.2), (ParamKey::PredatorReproductionRate, 0.1)],
+            vec![(ParamKey::PreyReproductionRate, 0.2), (ParamKey::PredatorReproductionRate, 0.3)],
+            vec![(ParamKey::PreyReproductionRate, 0.6), (ParamKey::PredatorReproductionRate, 0.1)],
+            vec![(ParamKey::PreyReproductionRate, 0.6), (ParamKey::PredatorReproductionRate, 0.3)],
+        ]);
+        assert_eq!(results.iter().map(|r| r.seed).collect::<Vec<_>>(), vec![42, 43, 44, 45]);
+        assert_eq!(run_sweep(base.clone(), varying, 5, 42).unwrap(), results);
+        assert!(run_sweep(base, vec![(ParamKey::PreyMovingRate, vec![0.5, 1.5])], 5, 0).is_err());
+    }
+
+    #[test]
+    fn parallel_sweep_matches_sequential_sweep() {
+        let base = SimulationConfig {
+            width: 12,
+            height: 12,
+            nb_prey_init: 30,
+            nb_predator_init: 15,
+            ..Default::default()
+        };
+        let varying = vec![
+            (ParamKey::PredatorHuntingRate, vec![0.2, 0.5, 0.8]),
+            (ParamKey::PreyMovingRate, vec![0.3, 0.9]),
+        ];
+        let sequential = run_sweep(base.clone(), varying.clone(), 10, 7).unwrap();
+        let parallel = run_sweep_parallel(base, varying, 10, 7).unwrap();
+        assert_eq!(parallel.len(), 6);
+        assert_eq!(parallel, sequential);
+    }
     
 }